@@ -0,0 +1,54 @@
+/* Plays a short game with a fixed input and prints what happened.
+ *
+ *   cargo build --release
+ *   cc examples/embed.c -Iinclude -Ltarget/release -lasteroids -o build/embed
+ *   LD_LIBRARY_PATH=target/release ./build/embed
+ */
+#include <stdio.h>
+#include <stdlib.h>
+#include <asteroids.h>
+
+int main(void) {
+  if (asteroids_abi_version() != ASTEROIDS_ABI_VERSION) {
+    fprintf(stderr, "Library ABI version %u doesn't match the header\n", asteroids_abi_version());
+    return 1;
+  }
+
+  AsteroidsWorld *world = asteroids_world_create(42);
+  for (int tick = 0; tick < 600; tick++) {
+    uint8_t input = ASTEROIDS_INPUT_LEFT | ASTEROIDS_INPUT_FIRE;
+    size_t event_count = asteroids_world_step(world, input, ASTEROIDS_FIXED_DELTA_TIME);
+
+    AsteroidsEvent events[16];
+    size_t written = event_count < 16 ? event_count : 16;
+    asteroids_world_events(world, events, written);
+    for (size_t i = 0; i < written; i++) {
+      if (events[i].kind == ASTEROIDS_EVENT_SHIP_CRASHED) {
+        printf("Tick %d: crashed with a score of %u\n", tick, events[i].score);
+      }
+    }
+  }
+
+  AsteroidsStats stats;
+  asteroids_world_stats(world, &stats);
+  size_t asteroid_count = asteroids_world_asteroids(world, NULL, 0);
+  AsteroidsAsteroid *asteroids = malloc(asteroid_count * sizeof(AsteroidsAsteroid));
+  asteroids_world_asteroids(world, asteroids, asteroid_count);
+  printf("Score %u, wave %u, %zu asteroids after %.1fs\n", stats.score, stats.wave, asteroid_count, stats.time);
+  for (size_t i = 0; i < asteroid_count; i++) {
+    printf("  asteroid at (%.1f, %.1f), scale %.1f\n", asteroids[i].x, asteroids[i].y, asteroids[i].scale);
+  }
+  free(asteroids);
+
+  uint32_t *pixels = malloc(ASTEROIDS_CANVAS_WIDTH * ASTEROIDS_CANVAS_HEIGHT * sizeof(uint32_t));
+  asteroids_world_render(world, pixels, ASTEROIDS_CANVAS_WIDTH, ASTEROIDS_CANVAS_HEIGHT);
+  size_t lit = 0;
+  for (size_t i = 0; i < ASTEROIDS_CANVAS_WIDTH * ASTEROIDS_CANVAS_HEIGHT; i++) {
+    lit += pixels[i] != 0;
+  }
+  printf("%zu pixels are lit\n", lit);
+  free(pixels);
+
+  asteroids_world_destroy(world);
+  return 0;
+}
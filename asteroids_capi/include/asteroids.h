@@ -0,0 +1,139 @@
+/*
+ * C interface to the Rust Asteroids simulation.
+ *
+ * All functions accept NULL world pointers and do nothing (or return 0) for
+ * them. Functions that fill caller-provided arrays return the total number of
+ * elements and write at most `capacity` of them, so the required capacity can
+ * be queried by passing NULL and 0.
+ *
+ * The layout of everything in this header is covered by ASTEROIDS_ABI_VERSION.
+ * It is only ever extended by new functions; a change to an existing struct or
+ * signature bumps the version.
+ */
+#ifndef ASTEROIDS_H
+#define ASTEROIDS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define ASTEROIDS_ABI_VERSION 1
+
+/* Resolution the game is drawn at, see asteroids_world_render. */
+#define ASTEROIDS_CANVAS_WIDTH 320
+#define ASTEROIDS_CANVAS_HEIGHT 288
+
+/* Seconds per update the game is designed for. */
+#define ASTEROIDS_FIXED_DELTA_TIME (1.0f / 60.0f)
+
+/* Input bitmask bits, the same values replays store. */
+#define ASTEROIDS_INPUT_LEFT (1u << 0)
+#define ASTEROIDS_INPUT_RIGHT (1u << 1)
+#define ASTEROIDS_INPUT_THRUST (1u << 2)
+#define ASTEROIDS_INPUT_REVERSE (1u << 3)
+/* Fires a bullet when the weapon cooldown allows it. */
+#define ASTEROIDS_INPUT_FIRE (1u << 4)
+/* Keeps the shield up while held and there is energy left. */
+#define ASTEROIDS_INPUT_SHIELD (1u << 5)
+/* Equips the next weapon: blaster, laser, missile, mine. */
+#define ASTEROIDS_INPUT_SWITCH_WEAPON (1u << 6)
+
+/* Values of AsteroidsEvent.kind */
+#define ASTEROIDS_EVENT_ASTEROID_DESTROYED 0
+#define ASTEROIDS_EVENT_SHIP_CRASHED 1
+/* A ship of a multiplayer save crashed and the game goes on. */
+#define ASTEROIDS_EVENT_LIFE_LOST 2
+/* A round of a versus save ended, score is the round. */
+#define ASTEROIDS_EVENT_ROUND_OVER 3
+/* A player won a versus save, score is the player's number starting at 1. */
+#define ASTEROIDS_EVENT_MATCH_WON 4
+/* A player of a save with power-ups collected one, score is the player's
+ * number starting at 1. */
+#define ASTEROIDS_EVENT_POWER_UP_COLLECTED 5
+
+/* Return values of the functions that can fail. */
+#define ASTEROIDS_OK 0
+#define ASTEROIDS_ERROR_ARGUMENT (-1)
+#define ASTEROIDS_ERROR_IO (-2)
+#define ASTEROIDS_ERROR_FORMAT (-3)
+
+/* Opaque handle to a running game. */
+typedef struct AsteroidsWorld AsteroidsWorld;
+
+typedef struct {
+  float x, y;
+  float velocity_x, velocity_y;
+  float rotation; /* radians */
+  float acceleration;
+} AsteroidsShip;
+
+typedef struct {
+  float x, y;
+  float velocity_x, velocity_y;
+  float rotation; /* radians */
+  float scale;
+} AsteroidsAsteroid;
+
+typedef struct {
+  float x, y;
+  float velocity_x, velocity_y;
+  /* Number of times the bullet wrapped around the screen edges. */
+  uint32_t wrap_count;
+} AsteroidsBullet;
+
+typedef struct {
+  uint64_t seed;
+  uint32_t score;
+  uint32_t high_score;
+  uint32_t wave;
+  /* Simulated seconds since the game started. */
+  double time;
+} AsteroidsStats;
+
+typedef struct {
+  uint32_t kind;
+  /* Score after the hit, the lives left after a lost life, or the score
+   * before the reset for a crash. */
+  uint32_t score;
+  /* Only set for ASTEROIDS_EVENT_SHIP_CRASHED */
+  uint32_t high_score;
+} AsteroidsEvent;
+
+/* ASTEROIDS_ABI_VERSION of the loaded library. */
+uint32_t asteroids_abi_version(void);
+
+/* Starts a new game, free it with asteroids_world_destroy. */
+AsteroidsWorld *asteroids_world_create(uint64_t seed);
+void asteroids_world_destroy(AsteroidsWorld *world);
+/* Restarts the game with a new seed. */
+void asteroids_world_reset(AsteroidsWorld *world, uint64_t seed);
+
+/* Advances the game by one update with the ASTEROIDS_INPUT_* bits in `input`.
+ * Returns the number of events the update produced. */
+size_t asteroids_world_step(AsteroidsWorld *world, uint8_t input, float delta_time);
+
+/* Events of the last asteroids_world_step. */
+size_t asteroids_world_events(const AsteroidsWorld *world, AsteroidsEvent *out, size_t capacity);
+
+void asteroids_world_stats(const AsteroidsWorld *world, AsteroidsStats *out);
+void asteroids_world_ship(const AsteroidsWorld *world, AsteroidsShip *out);
+size_t asteroids_world_asteroids(const AsteroidsWorld *world, AsteroidsAsteroid *out, size_t capacity);
+size_t asteroids_world_bullets(const AsteroidsWorld *world, AsteroidsBullet *out, size_t capacity);
+
+/* Draws the game into `buffer`, `width * height` 0x00RRGGBB pixels in row
+ * major order. The canvas is scaled to the buffer size, multiples of
+ * ASTEROIDS_CANVAS_WIDTH x ASTEROIDS_CANVAS_HEIGHT give the sharpest result. */
+int32_t asteroids_world_render(const AsteroidsWorld *world, uint32_t *buffer, size_t width, size_t height);
+
+/* Save files compatible with the game's quick-save. */
+int32_t asteroids_world_save(const AsteroidsWorld *world, const char *path);
+int32_t asteroids_world_load(AsteroidsWorld *world, const char *path);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ASTEROIDS_H */
@@ -0,0 +1,309 @@
+//! C ABI for the simulation in `asteroids_rust`, declared in `include/asteroids.h`.
+//!
+//! Keep the header in sync with this file: every `#[repr(C)]` type and
+//! exported function here has a counterpart there, and any change to an
+//! existing one bumps [`ABI_VERSION`].
+
+#![allow(clippy::missing_safety_doc)]
+
+use asteroids_rust::{
+    debug::DebugOverlay,
+    game::{Game, GameEvent, Input},
+    render,
+    save::{self, SaveError},
+};
+use std::{ffi::CStr, os::raw::c_char, slice};
+
+pub const ABI_VERSION: u32 = 1;
+
+pub const OK: i32 = 0;
+pub const ERROR_ARGUMENT: i32 = -1;
+pub const ERROR_IO: i32 = -2;
+pub const ERROR_FORMAT: i32 = -3;
+
+pub const EVENT_ASTEROID_DESTROYED: u32 = 0;
+pub const EVENT_SHIP_CRASHED: u32 = 1;
+pub const EVENT_LIFE_LOST: u32 = 2;
+pub const EVENT_ROUND_OVER: u32 = 3;
+pub const EVENT_MATCH_WON: u32 = 4;
+pub const EVENT_POWER_UP_COLLECTED: u32 = 5;
+
+pub struct AsteroidsWorld {
+    game: Game,
+    debug_overlay: DebugOverlay,
+}
+
+#[repr(C)]
+pub struct AsteroidsShip {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub rotation: f32,
+    pub acceleration: f32,
+}
+
+#[repr(C)]
+pub struct AsteroidsAsteroid {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+#[repr(C)]
+pub struct AsteroidsBullet {
+    pub x: f32,
+    pub y: f32,
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub wrap_count: u32,
+}
+
+#[repr(C)]
+pub struct AsteroidsStats {
+    pub seed: u64,
+    pub score: u32,
+    pub high_score: u32,
+    pub wave: u32,
+    pub time: f64,
+}
+
+#[repr(C)]
+pub struct AsteroidsEvent {
+    pub kind: u32,
+    pub score: u32,
+    pub high_score: u32,
+}
+
+/// Copies as many of `items` as fit into `out` and returns the total count.
+unsafe fn fill<T>(items: impl ExactSizeIterator<Item = T>, out: *mut T, capacity: usize) -> usize {
+    let count = items.len();
+    if !out.is_null() {
+        let out = slice::from_raw_parts_mut(out, capacity);
+        for (slot, item) in out.iter_mut().zip(items) {
+            *slot = item;
+        }
+    }
+    count
+}
+
+unsafe fn c_path<'a>(path: *const c_char) -> Option<&'a str> {
+    if path.is_null() {
+        return None;
+    }
+    CStr::from_ptr(path).to_str().ok()
+}
+
+fn error_code(e: SaveError) -> i32 {
+    match e {
+        SaveError::Io(_) => ERROR_IO,
+        _ => ERROR_FORMAT,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_abi_version() -> u32 {
+    ABI_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_world_create(seed: u64) -> *mut AsteroidsWorld {
+    Box::into_raw(Box::new(AsteroidsWorld {
+        game: Game::new(seed),
+        debug_overlay: DebugOverlay::default(),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_destroy(world: *mut AsteroidsWorld) {
+    if !world.is_null() {
+        drop(Box::from_raw(world));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_world_reset(world: Option<&mut AsteroidsWorld>, seed: u64) {
+    if let Some(world) = world {
+        world.game = Game::new(seed);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_world_step(
+    world: Option<&mut AsteroidsWorld>,
+    input: u8,
+    delta_time: f32,
+) -> usize {
+    let Some(world) = world else { return 0 };
+    world.game.update(&Input::from_bits(input), delta_time);
+    world.game.events.len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_events(
+    world: Option<&AsteroidsWorld>,
+    out: *mut AsteroidsEvent,
+    capacity: usize,
+) -> usize {
+    let Some(world) = world else { return 0 };
+    let events = world.game.events.iter().map(|event| match *event {
+        GameEvent::AsteroidDestroyed { score, .. } => AsteroidsEvent {
+            kind: EVENT_ASTEROID_DESTROYED,
+            score,
+            high_score: 0,
+        },
+        GameEvent::LifeLost { lives, .. } => AsteroidsEvent {
+            kind: EVENT_LIFE_LOST,
+            score: lives,
+            high_score: 0,
+        },
+        GameEvent::RoundOver { round, .. } => AsteroidsEvent {
+            kind: EVENT_ROUND_OVER,
+            score: round,
+            high_score: 0,
+        },
+        GameEvent::MatchWon { player } => AsteroidsEvent {
+            kind: EVENT_MATCH_WON,
+            score: player as u32 + 1,
+            high_score: 0,
+        },
+        GameEvent::PowerUpCollected { player, .. } => AsteroidsEvent {
+            kind: EVENT_POWER_UP_COLLECTED,
+            score: player as u32 + 1,
+            high_score: 0,
+        },
+        GameEvent::ShipCrashed { score, high_score } => AsteroidsEvent {
+            kind: EVENT_SHIP_CRASHED,
+            score,
+            high_score,
+        },
+    });
+    fill(events, out, capacity)
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_world_stats(
+    world: Option<&AsteroidsWorld>,
+    out: Option<&mut AsteroidsStats>,
+) {
+    let (Some(world), Some(out)) = (world, out) else {
+        return;
+    };
+    let game = &world.game;
+    *out = AsteroidsStats {
+        seed: game.seed,
+        score: game.score,
+        high_score: game.high_score,
+        wave: game.wave,
+        time: game.time,
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn asteroids_world_ship(
+    world: Option<&AsteroidsWorld>,
+    out: Option<&mut AsteroidsShip>,
+) {
+    let (Some(world), Some(out)) = (world, out) else {
+        return;
+    };
+    let ship = &world.game.players[0].ship;
+    *out = AsteroidsShip {
+        x: ship.transform.pos.x,
+        y: ship.transform.pos.y,
+        velocity_x: ship.velocity.x,
+        velocity_y: ship.velocity.y,
+        rotation: ship.transform.rot,
+        acceleration: ship.acc,
+    };
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_asteroids(
+    world: Option<&AsteroidsWorld>,
+    out: *mut AsteroidsAsteroid,
+    capacity: usize,
+) -> usize {
+    let Some(world) = world else { return 0 };
+    let asteroids = world.game.asteroids.iter().map(|a| AsteroidsAsteroid {
+        x: a.transform.pos.x,
+        y: a.transform.pos.y,
+        velocity_x: a.velocity.x,
+        velocity_y: a.velocity.y,
+        rotation: a.transform.rot,
+        scale: a.transform.scale,
+    });
+    fill(asteroids, out, capacity)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_bullets(
+    world: Option<&AsteroidsWorld>,
+    out: *mut AsteroidsBullet,
+    capacity: usize,
+) -> usize {
+    let Some(world) = world else { return 0 };
+    let bullets = world.game.bullets.iter().map(|b| {
+        let velocity = b.velocity();
+        AsteroidsBullet {
+            x: b.pos.x,
+            y: b.pos.y,
+            velocity_x: velocity.x,
+            velocity_y: velocity.y,
+            wrap_count: b.wrap_count as u32,
+        }
+    });
+    fill(bullets, out, capacity)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_render(
+    world: Option<&AsteroidsWorld>,
+    buffer: *mut u32,
+    width: usize,
+    height: usize,
+) -> i32 {
+    let Some(world) = world else {
+        return ERROR_ARGUMENT;
+    };
+    if buffer.is_null() || width == 0 || height == 0 {
+        return ERROR_ARGUMENT;
+    }
+    let buffer = slice::from_raw_parts_mut(buffer, width * height);
+    render::render(buffer, (width, height), &world.game, &world.debug_overlay);
+    OK
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_save(
+    world: Option<&AsteroidsWorld>,
+    path: *const c_char,
+) -> i32 {
+    let (Some(world), Some(path)) = (world, c_path(path)) else {
+        return ERROR_ARGUMENT;
+    };
+    match save::save(&world.game, path) {
+        Ok(()) => OK,
+        Err(e) => error_code(e),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn asteroids_world_load(
+    world: Option<&mut AsteroidsWorld>,
+    path: *const c_char,
+) -> i32 {
+    let (Some(world), Some(path)) = (world, c_path(path)) else {
+        return ERROR_ARGUMENT;
+    };
+    match save::load(path) {
+        Ok(game) => {
+            world.game = game;
+            OK
+        }
+        Err(e) => error_code(e),
+    }
+}
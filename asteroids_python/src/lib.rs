@@ -0,0 +1,362 @@
+//! Python bindings for the simulation in `asteroids_rust`.
+//!
+//! ```python
+//! import asteroids
+//!
+//! env = asteroids.Env(observation="features", max_steps=10_000)
+//! obs = env.reset(seed=42)
+//! while True:
+//!     obs, reward, done, info = env.step(asteroids.THRUST | asteroids.FIRE)
+//!     if done:
+//!         break
+//! ```
+
+use asteroids_rust::{
+    env::{Env, EnvConfig, Info, Observation, ObservationKind, FEATURE_COUNT},
+    game::{Input, FIXED_DELTA_TIME},
+    physics::{AsteroidPhysics, DEFAULT_RESTITUTION},
+    replay,
+    save::{self, SaveError},
+    spectate::Broadcaster,
+    weapon::Ballistics,
+    CANVAS_HEIGHT, CANVAS_WIDTH,
+};
+use numpy::{PyArray1, PyArray2, PyArray3, PyArrayMethods};
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+fn to_py_err(e: SaveError) -> PyErr {
+    match e {
+        SaveError::Io(e) => PyIOError::new_err(e.to_string()),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// Builds an `(n, columns)` array from rows of `columns` values.
+fn rows<'py>(
+    py: Python<'py>,
+    columns: usize,
+    values: Vec<f32>,
+) -> PyResult<Bound<'py, PyArray2<f32>>> {
+    let n = values.len() / columns;
+    PyArray1::from_vec(py, values).reshape([n, columns])
+}
+
+/// Converts 0RGB pixels to an `(height, width, 3)` array.
+fn pixels_to_array<'py>(py: Python<'py>, pixels: &[u32]) -> PyResult<Bound<'py, PyArray3<u8>>> {
+    let rgb: Vec<u8> = pixels
+        .iter()
+        .flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8])
+        .collect();
+    PyArray1::from_vec(py, rgb).reshape([CANVAS_HEIGHT, CANVAS_WIDTH, 3])
+}
+
+fn info_to_dict<'py>(py: Python<'py>, info: &Info) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("score", info.score)?;
+    dict.set_item("wave", info.wave)?;
+    dict.set_item("steps", info.steps)?;
+    dict.set_item("asteroids", info.asteroids)?;
+    dict.set_item("crashed", info.crashed)?;
+    dict.set_item("truncated", info.truncated)?;
+    Ok(dict)
+}
+
+/// The game as a reinforcement learning environment, see `asteroids_rust::env`.
+#[pyclass(name = "Env", module = "asteroids")]
+struct PyEnv {
+    env: Env,
+}
+
+impl PyEnv {
+    fn observation<'py>(
+        &self,
+        py: Python<'py>,
+        observation: Observation,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let features = || PyArray1::from_vec(py, observation.features.clone());
+        Ok(match self.env.config().observation {
+            ObservationKind::Features => features().into_any(),
+            ObservationKind::Pixels => pixels_to_array(py, &observation.pixels)?.into_any(),
+            ObservationKind::Both => {
+                let dict = PyDict::new(py);
+                dict.set_item("features", features())?;
+                dict.set_item("pixels", pixels_to_array(py, &observation.pixels)?)?;
+                dict.into_any()
+            }
+        })
+    }
+}
+
+#[pymethods]
+impl PyEnv {
+    /// `observation` is "features", "pixels" or "both". Every step repeats the
+    /// action for `frame_skip` updates of `delta_time` seconds. With
+    /// `asteroid_physics` the asteroids bounce off each other, keeping
+    /// `restitution` of their approach speed. With `power_ups` shot asteroids
+    /// drop power-ups. With `arcade_bullets` bullets keep the ship's momentum,
+    /// have a fixed range and only four are out at once.
+    #[allow(clippy::too_many_arguments)]
+    #[new]
+    #[pyo3(signature = (
+        observation = "features",
+        frame_skip = 1,
+        max_steps = None,
+        delta_time = FIXED_DELTA_TIME,
+        reward_per_asteroid = 1.,
+        crash_reward = -10.,
+        asteroid_physics = false,
+        restitution = DEFAULT_RESTITUTION,
+        power_ups = false,
+        arcade_bullets = false,
+    ))]
+    fn new(
+        observation: &str,
+        frame_skip: u32,
+        max_steps: Option<u64>,
+        delta_time: f32,
+        reward_per_asteroid: f32,
+        crash_reward: f32,
+        asteroid_physics: bool,
+        restitution: f32,
+        power_ups: bool,
+        arcade_bullets: bool,
+    ) -> PyResult<Self> {
+        let observation = match observation {
+            "features" => ObservationKind::Features,
+            "pixels" => ObservationKind::Pixels,
+            "both" => ObservationKind::Both,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown observation '{other}', expected 'features', 'pixels' or 'both'"
+                )))
+            }
+        };
+        if frame_skip == 0 {
+            return Err(PyValueError::new_err("frame_skip must be at least 1"));
+        }
+        if !(0. ..=1.).contains(&restitution) {
+            return Err(PyValueError::new_err("restitution must be between 0 and 1"));
+        }
+
+        Ok(Self {
+            env: Env::new(EnvConfig {
+                delta_time,
+                frame_skip,
+                max_steps,
+                observation,
+                reward_per_asteroid,
+                crash_reward,
+                asteroid_physics: asteroid_physics.then_some(AsteroidPhysics { restitution }),
+                power_ups,
+                ballistics: if arcade_bullets {
+                    Ballistics::ARCADE
+                } else {
+                    Ballistics::default()
+                },
+            }),
+        })
+    }
+
+    /// Starts a new episode and returns the first observation.
+    #[pyo3(signature = (seed = 0))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<Bound<'py, PyAny>> {
+        let observation = self.env.reset(seed);
+        self.observation(py, observation)
+    }
+
+    /// Applies `action`, a bitmask of `LEFT`, `RIGHT`, `THRUST`, `REVERSE`,
+    /// `FIRE`, `SHIELD` and `SWITCH_WEAPON`. Returns `(observation, reward, done, info)`.
+    #[pyo3(signature = (action, delta_time = None))]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: u8,
+        delta_time: Option<f32>,
+    ) -> PyResult<(Bound<'py, PyAny>, f32, bool, Bound<'py, PyDict>)> {
+        let action = Input::from_bits(action);
+        let (observation, reward, done, info) = match delta_time {
+            Some(delta_time) => self.env.step_with_delta(&action, delta_time),
+            None => self.env.step(&action),
+        };
+        Ok((
+            self.observation(py, observation)?,
+            reward,
+            done,
+            info_to_dict(py, &info)?,
+        ))
+    }
+
+    /// The feature vector, regardless of the configured observation.
+    fn features<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        let mut features = vec![0.; FEATURE_COUNT];
+        self.env.write_features(&mut features);
+        PyArray1::from_vec(py, features)
+    }
+
+    /// The rendered canvas as an `(height, width, 3)` uint8 array.
+    fn pixels<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PyArray3<u8>>> {
+        pixels_to_array(py, self.env.render())
+    }
+
+    /// `[x, y, velocity_x, velocity_y, rotation, acceleration]`
+    fn ship<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        let ship = &self.env.game().players[0].ship;
+        let t = &ship.transform;
+        PyArray1::from_vec(
+            py,
+            vec![
+                t.pos.x,
+                t.pos.y,
+                ship.velocity.x,
+                ship.velocity.y,
+                t.rot,
+                ship.acc,
+            ],
+        )
+    }
+
+    /// One `[x, y, velocity_x, velocity_y, scale]` row per asteroid.
+    fn asteroids<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let values = self
+            .env
+            .game()
+            .asteroids
+            .iter()
+            .flat_map(|a| {
+                let t = &a.transform;
+                [t.pos.x, t.pos.y, a.velocity.x, a.velocity.y, t.scale]
+            })
+            .collect();
+        rows(py, 5, values)
+    }
+
+    /// One `[x, y, velocity_x, velocity_y, wrap_count]` row per bullet.
+    fn bullets<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let values = self
+            .env
+            .game()
+            .bullets
+            .iter()
+            .flat_map(|b| {
+                let velocity = b.velocity();
+                [
+                    b.pos.x,
+                    b.pos.y,
+                    velocity.x,
+                    velocity.y,
+                    b.wrap_count as f32,
+                ]
+            })
+            .collect();
+        rows(py, 5, values)
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.env.game().score
+    }
+
+    #[getter]
+    fn high_score(&self) -> u32 {
+        self.env.game().high_score
+    }
+
+    #[getter]
+    fn wave(&self) -> u32 {
+        self.env.game().wave
+    }
+
+    /// Simulated seconds since the game started.
+    #[getter]
+    fn time(&self) -> f64 {
+        self.env.game().time
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.env.game().seed
+    }
+
+    /// Writes the game to a save file that the game itself can load.
+    fn save_state(&self, path: &str) -> PyResult<()> {
+        save::save(self.env.game(), path).map_err(to_py_err)
+    }
+
+    /// Starts a new episode from a save file and returns its observation.
+    fn load_state<'py>(&mut self, py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyAny>> {
+        let game = save::load(path).map_err(to_py_err)?;
+        let observation = self.env.reset_to(game);
+        self.observation(py, observation)
+    }
+
+    /// Streams the episodes to `asteroids_viewer` on TCP `port`, `None` stops.
+    #[pyo3(signature = (port = None))]
+    fn spectate(&mut self, port: Option<u16>) -> PyResult<()> {
+        let broadcaster = port
+            .map(Broadcaster::bind)
+            .transpose()
+            .map_err(|e| PyIOError::new_err(e.to_string()))?;
+        self.env.spectate(broadcaster);
+        Ok(())
+    }
+}
+
+/// A recorded game, see `asteroids_rust::replay`.
+#[pyclass(name = "Replay", module = "asteroids")]
+struct PyReplay {
+    replay: replay::Replay,
+}
+
+#[pymethods]
+impl PyReplay {
+    #[staticmethod]
+    fn load(path: &str) -> PyResult<Self> {
+        let replay = replay::Replay::load(path).map_err(to_py_err)?;
+        Ok(Self { replay })
+    }
+
+    /// The input bitmask of the first player for every tick.
+    #[getter]
+    fn inputs<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        PyArray1::from_vec(py, self.replay.ticks.iter().map(|t| t.input).collect())
+    }
+
+    /// The delta time of every tick in seconds.
+    #[getter]
+    fn delta_times<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        PyArray1::from_vec(py, self.replay.ticks.iter().map(|t| t.delta_time).collect())
+    }
+
+    fn __len__(&self) -> usize {
+        self.replay.ticks.len()
+    }
+
+    /// Starts a new episode of `env` from the state the replay starts from.
+    /// Passing `inputs[i]` and `delta_times[i]` to `env.step` then reproduces
+    /// the recorded game as long as `frame_skip` is 1.
+    fn reset<'py>(&self, py: Python<'py>, env: &mut PyEnv) -> PyResult<Bound<'py, PyAny>> {
+        let observation = env.env.reset_to(self.replay.start.clone());
+        env.observation(py, observation)
+    }
+}
+
+#[pymodule]
+fn asteroids(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyEnv>()?;
+    m.add_class::<PyReplay>()?;
+    m.add("LEFT", Input::LEFT)?;
+    m.add("RIGHT", Input::RIGHT)?;
+    m.add("THRUST", Input::THRUST)?;
+    m.add("REVERSE", Input::REVERSE)?;
+    m.add("FIRE", Input::FIRE)?;
+    m.add("SHIELD", Input::SHIELD)?;
+    m.add("SWITCH_WEAPON", Input::SWITCH_WEAPON)?;
+    m.add("FEATURE_COUNT", FEATURE_COUNT)?;
+    m.add("CANVAS_WIDTH", CANVAS_WIDTH)?;
+    m.add("CANVAS_HEIGHT", CANVAS_HEIGHT)?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+//! Plays random actions in the headless environment and reports throughput.
+//!
+//! `cargo run --release --no-default-features --example random_agent`
+
+use asteroids_rust::{
+    env::{Env, EnvConfig},
+    game::Input,
+};
+use rand::Rng;
+use std::time::Instant;
+
+fn main() {
+    let mut env = Env::new(EnvConfig {
+        max_steps: Some(10_000),
+        ..Default::default()
+    });
+    let mut rng = rand::thread_rng();
+
+    let episodes = 20;
+    let mut steps = 0;
+    let mut total_score = 0;
+    let start = Instant::now();
+    for seed in 0..episodes {
+        env.reset(seed);
+        loop {
+            let action = Input::from_bits(rng.gen_range(0..32));
+            let (_, _, done, info) = env.step(&action);
+            if done {
+                steps += info.steps;
+                total_score += info.score;
+                break;
+            }
+        }
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    println!(
+        "{episodes} episodes, {steps} steps in {elapsed:.2}s ({:.0} steps/s), mean score {:.2}",
+        steps as f64 / elapsed,
+        total_score as f64 / episodes as f64
+    );
+}
@@ -0,0 +1,42 @@
+-- Example mod, run it with `cargo run --release -- --script mods/example.lua`.
+-- The game reloads this file whenever it changes, see src/script.rs for the API.
+
+-- Small asteroids are harder to hit, so they are worth more.
+function asteroid_score(asteroid)
+  if asteroid.scale < 6 then
+    return 3
+  end
+  return 1
+end
+
+-- Asteroids always split in two pieces flying apart.
+function split_asteroid(asteroid, ctx)
+  local angle = ctx.random() * math.pi * 2
+  local speed = 30
+  local pieces = {}
+  for i = 0, 1 do
+    local a = angle + i * math.pi
+    pieces[#pieces + 1] = {
+      scale = asteroid.scale * 0.6,
+      vx = math.cos(a) * speed,
+      vy = math.sin(a) * speed,
+    }
+  end
+  return pieces
+end
+
+-- Every third wave is a single huge asteroid.
+function wave_asteroids(wave, ctx)
+  if wave > 0 and wave % 3 == 0 then
+    local boss = ctx.random_asteroid()
+    boss.scale = 24
+    return { boss }
+  end
+  return nil
+end
+
+function on_event(event, ctx)
+  if event.kind == "ship_crashed" then
+    log("crashed with " .. event.score .. " points")
+  end
+end
@@ -0,0 +1,95 @@
+//! Diffs the conformance trace of a port against a reference trace, see
+//! `asteroids_rust::trace`. Exits with 1 if the traces differ.
+
+use asteroids_rust::trace::{self, Tolerance, Trace};
+use std::process::exit;
+
+const USAGE: &str = "Usage: trace_check <reference> <trace> [OPTIONS]
+
+Options:
+    --position <pixels>    Position tolerance [default: 0.01]
+    --velocity <pixels/s>  Velocity tolerance [default: 0.01]
+    --rotation <radians>   Rotation tolerance [default: 0.001]
+    --scale <n>            Asteroid scale tolerance [default: 0.001]
+    --ignore <a,b,..>      Skip comparing 'asteroids' and/or 'bullets'
+    --max-errors <n>       Stop listing differences after <n> [default: 20]
+    --help                 Print this message";
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for {flag}"))
+}
+
+fn parse_args() -> Result<(Vec<String>, Tolerance, usize), String> {
+    let mut paths = vec![];
+    let mut tolerance = Tolerance::default();
+    let mut max_errors = 20;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--position" => tolerance.position = parse_value(&arg, args.next())?,
+            "--velocity" => tolerance.velocity = parse_value(&arg, args.next())?,
+            "--rotation" => tolerance.rotation = parse_value(&arg, args.next())?,
+            "--scale" => tolerance.scale = parse_value(&arg, args.next())?,
+            "--ignore" => {
+                let list: String = parse_value(&arg, args.next())?;
+                for what in list.split(',') {
+                    match what {
+                        "asteroids" => tolerance.ignore_asteroids = true,
+                        "bullets" => tolerance.ignore_bullets = true,
+                        _ => return Err(format!("can't ignore '{what}'")),
+                    }
+                }
+            }
+            "--max-errors" => max_errors = parse_value(&arg, args.next())?,
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{arg}'")),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        return Err("expected a reference trace and a trace to check".to_string());
+    }
+    Ok((paths, tolerance, max_errors))
+}
+
+fn main() {
+    let (paths, tolerance, max_errors) = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+    let load = |path: &String| {
+        Trace::load(path).unwrap_or_else(|e| {
+            eprintln!("Couldn't load trace '{path}': {e}");
+            exit(2);
+        })
+    };
+    let reference = load(&paths[0]);
+    let other = load(&paths[1]);
+
+    let mismatches = trace::compare(&reference, &other, &tolerance);
+    if mismatches.is_empty() {
+        println!("{} ticks match", reference.ticks.len());
+        return;
+    }
+
+    for mismatch in mismatches.iter().take(max_errors) {
+        println!("{mismatch}");
+    }
+    if mismatches.len() > max_errors {
+        println!("... and {} more", mismatches.len() - max_errors);
+    }
+    println!(
+        "{} differences, first at tick {}",
+        mismatches.len(),
+        mismatches[0].tick
+    );
+    exit(1);
+}
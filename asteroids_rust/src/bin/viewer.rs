@@ -0,0 +1,157 @@
+//! Watches a game broadcast with `--spectate`, see `asteroids_rust::spectate`.
+
+use asteroids_rust::{
+    debug::DebugOverlay,
+    font, render,
+    spectate::{Spectator, DEFAULT_SPECTATE_PORT},
+    CANVAS_HEIGHT, CANVAS_WIDTH,
+};
+use framebrush::{Canvas, WHITE};
+use std::{
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+const SCREEN_WIDTH: u32 = 640;
+const SCREEN_HEIGHT: u32 = 576;
+/// Time between two connection attempts while the broadcast is unreachable.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+const USAGE: &str = "Usage: asteroids_viewer [ADDRESS]
+
+Watches a game started with --spectate <port>.
+
+Arguments:
+    [ADDRESS]  Address of the game [default: 127.0.0.1:7100]
+
+Keys:
+    [Z] hitboxes, [F1] stats, [F3] velocities, [F4] asteroid bounds, [F5] bullet wrap counts,
+    [F7] collision grid";
+
+fn main() {
+    let mut address = format!("127.0.0.1:{DEFAULT_SPECTATE_PORT}");
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option '{arg}'\n\n{USAGE}");
+                std::process::exit(2);
+            }
+            _ => address = arg,
+        }
+    }
+
+    let mut spectator = Spectator::connect(address);
+    // Whether the viewer was connected since the last failure was reported
+    let mut connected = true;
+    let mut last_attempt = Instant::now();
+    let mut debug_overlay = DebugOverlay::default();
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_inner_size(LogicalSize::new(SCREEN_WIDTH, SCREEN_HEIGHT))
+        .with_title("Asteroids Viewer")
+        .build(&event_loop)
+        .unwrap();
+
+    let context = unsafe { softbuffer::Context::new(&window) }.unwrap();
+    let mut surface = unsafe { softbuffer::Surface::new(&context, &window) }.unwrap();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::WaitUntil(
+            Instant::now()
+                .checked_add(Duration::from_micros(1_000_000 / 144))
+                .unwrap(),
+        );
+
+        match event {
+            Event::WindowEvent {
+                window_id,
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(keycode),
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    },
+            } if window_id == window.id() => {
+                let view = match keycode {
+                    VirtualKeyCode::Z => &mut debug_overlay.hitbox,
+                    VirtualKeyCode::F1 => &mut debug_overlay.stats,
+                    VirtualKeyCode::F3 => &mut debug_overlay.velocities,
+                    VirtualKeyCode::F4 => &mut debug_overlay.bounds,
+                    VirtualKeyCode::F5 => &mut debug_overlay.wrap_counts,
+                    VirtualKeyCode::F7 => &mut debug_overlay.grid,
+                    _ => return,
+                };
+                *view = !*view;
+            }
+
+            Event::MainEventsCleared => {
+                let alive = spectator.update();
+                connected |= spectator.is_live();
+                if !alive {
+                    if connected {
+                        let reason = spectator.error().unwrap_or("connection lost");
+                        println!("[Viewer] {reason}, retrying...");
+                        connected = false;
+                    }
+                    if last_attempt.elapsed() > RECONNECT_INTERVAL {
+                        spectator.reconnect();
+                        last_attempt = Instant::now();
+                    }
+                }
+                window.request_redraw();
+            }
+
+            Event::RedrawRequested(id) if id == window.id() => {
+                let (width, height) = {
+                    let window_size = window.inner_size();
+
+                    (window_size.width, window_size.height)
+                };
+
+                if let (Some(width_nonzero), Some(height_nonzero)) =
+                    (NonZeroU32::new(width), NonZeroU32::new(height))
+                {
+                    surface.resize(width_nonzero, height_nonzero).unwrap();
+                    let mut buffer = surface.buffer_mut().unwrap();
+                    let mut canvas = Canvas::new(
+                        &mut buffer,
+                        (width as usize, height as usize),
+                        (CANVAS_WIDTH, CANVAS_HEIGHT),
+                    );
+                    match spectator.game() {
+                        Some(game) => render::draw(&mut canvas, game, &debug_overlay),
+                        None => canvas.fill(0),
+                    }
+                    if !spectator.is_live() {
+                        let text = format!("WAITING FOR {}", spectator.address());
+                        font::draw_text(&mut canvas, 4, CANVAS_HEIGHT as i32 - 10, &text, &WHITE);
+                    }
+
+                    buffer.present().expect("Couldn't present frame buffer.");
+                }
+            }
+
+            Event::WindowEvent {
+                window_id: id,
+                event: WindowEvent::CloseRequested,
+            } if id == window.id() => *control_flow = ControlFlow::Exit,
+
+            _ => (),
+        }
+    });
+}
@@ -0,0 +1,346 @@
+//! A computer pilot that plays with the same inputs as a human, and a
+//! benchmark that measures how well it does.
+
+use crate::game::{
+    Asteroid, Game, GameEvent, Input, Ship, BULLET_SPEED, DANGER_ZONE, FIXED_DELTA_TIME,
+};
+use crate::math::wrap_delta;
+use crate::rules::Rules;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// Radians per second the ship turns, see `Game::update`.
+const TURN_SPEED: f32 = 3.5;
+/// Rough radius of the ship's hitbox.
+const SHIP_RADIUS: f32 = 6.;
+/// Simulated seconds after which a benchmark game ends.
+pub const DEFAULT_BENCH_TIME: f64 = 600.;
+
+type V = (f32, f32);
+
+fn dot(a: V, b: V) -> f32 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn length(a: V) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// Shortest signed angle from `from` to `to`.
+fn angle_between(from: f32, to: f32) -> f32 {
+    let d = (to - from).rem_euclid(TAU);
+    if d > PI {
+        d - TAU
+    } else {
+        d
+    }
+}
+
+/// Earliest time a bullet fired now from the origin hits something at
+/// `offset` moving with `velocity`.
+fn intercept_time(offset: V, velocity: V, bullet_speed: f32) -> Option<f32> {
+    let a = dot(velocity, velocity) - bullet_speed * bullet_speed;
+    let b = 2. * dot(offset, velocity);
+    let c = dot(offset, offset);
+    if a.abs() < f32::EPSILON {
+        return (b < 0.).then(|| -c / b);
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(-b - root) / (2. * a), (-b + root) / (2. * a)]
+        .into_iter()
+        .filter(|t| *t > 0.)
+        .min_by(f32::total_cmp)
+}
+
+/// What the pilot knows about an asteroid relative to the ship.
+struct Contact {
+    /// Offset from the ship, taking the screen wrap into account.
+    offset: V,
+    /// Velocity relative to the ship.
+    relative_velocity: V,
+    velocity: V,
+    radius: f32,
+}
+
+impl Contact {
+    fn new(ship: &Ship, asteroid: &Asteroid) -> Self {
+        let offset = (
+            wrap_delta(
+                asteroid.transform.pos.x - ship.transform.pos.x,
+                CANVAS_WIDTH as f32,
+            ),
+            wrap_delta(
+                asteroid.transform.pos.y - ship.transform.pos.y,
+                CANVAS_HEIGHT as f32,
+            ),
+        );
+        let velocity = (asteroid.velocity.x, asteroid.velocity.y);
+        let (left, right, top, bottom) = asteroid.bounds();
+        Self {
+            offset,
+            relative_velocity: (velocity.0 - ship.velocity.x, velocity.1 - ship.velocity.y),
+            velocity,
+            radius: (right - left).max(bottom - top) / 2.,
+        }
+    }
+
+    /// Time and distance of the closest approach to the ship if neither changes course.
+    fn closest_approach(&self) -> (f32, f32) {
+        let speed = dot(self.relative_velocity, self.relative_velocity);
+        let t = if speed > 0. {
+            (-dot(self.offset, self.relative_velocity) / speed).max(0.)
+        } else {
+            0.
+        };
+        let at = (
+            self.offset.0 + self.relative_velocity.0 * t,
+            self.offset.1 + self.relative_velocity.1 * t,
+        );
+        (t, length(at))
+    }
+}
+
+/// Flies the ship: dodges asteroids that are about to hit it, otherwise
+/// turns towards the asteroid it can hit soonest and fires with enough lead
+/// to meet it.
+pub struct Autopilot {
+    /// Seconds ahead the pilot checks for collisions.
+    pub lookahead: f32,
+    /// Extra pixels kept between the ship and asteroids.
+    pub safety_margin: f32,
+    /// Speed above which the pilot brakes when it isn't dodging.
+    pub cruise_speed: f32,
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self {
+            lookahead: 1.2,
+            safety_margin: 8.,
+            cruise_speed: 25.,
+        }
+    }
+}
+
+impl Autopilot {
+    /// The input of the first player for the next update of `game`, which simulates `delta_time` seconds.
+    pub fn act(&self, game: &Game, delta_time: f32) -> Input {
+        self.act_as(game, 0, delta_time)
+    }
+
+    /// Like [`Autopilot::act`] but for the ship of `player`.
+    pub fn act_as(&self, game: &Game, player: usize, delta_time: f32) -> Input {
+        let ship = &game.players[player].ship;
+        let heading = ship.transform.rot + FRAC_PI_2;
+        let forward = (heading.cos(), heading.sin());
+        let velocity = (ship.velocity.x, ship.velocity.y);
+        let contacts: Vec<Contact> = game
+            .asteroids
+            .iter()
+            .map(|a| Contact::new(ship, a))
+            .collect();
+        let mut input = Input::default();
+
+        // Turning by less than this overshoots
+        let turn_step = TURN_SPEED * delta_time * game.time_scale;
+        let turn_towards = |input: &mut Input, target: f32| {
+            let delta = angle_between(heading, target);
+            if delta > turn_step / 2. {
+                input.right = true;
+            } else if delta < -turn_step / 2. {
+                input.left = true;
+            }
+            delta
+        };
+
+        // Dodge the most urgent threat by flying away from where it would hit
+        let threat = contacts
+            .iter()
+            .map(|c| (c, c.closest_approach()))
+            .filter(|(c, (t, distance))| {
+                *t < self.lookahead && *distance < c.radius + SHIP_RADIUS + self.safety_margin
+            })
+            .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+        if let Some((contact, (t, _))) = threat {
+            let at = (
+                contact.offset.0 + contact.relative_velocity.0 * t,
+                contact.offset.1 + contact.relative_velocity.1 * t,
+            );
+            // Away from the point of impact, or sideways for a head-on collision
+            let escape = if length(at) > 1. {
+                (-at.0, -at.1)
+            } else {
+                (-contact.relative_velocity.1, contact.relative_velocity.0)
+            };
+            let alignment = dot(forward, escape) / length(escape).max(f32::EPSILON);
+            if alignment > 0.3 {
+                input.thrust = true;
+            } else if alignment < -0.3 {
+                input.reverse = true;
+            }
+            // Line up with the escape direction, either way round
+            let escape_heading = escape.1.atan2(escape.0);
+            let target = if alignment >= 0. {
+                escape_heading
+            } else {
+                escape_heading + PI
+            };
+            turn_towards(&mut input, target);
+            return input;
+        }
+
+        // Shoot the asteroid that takes the least time to turn to and hit
+        let ballistics = &game.ballistics;
+        let shot = contacts
+            .iter()
+            .filter_map(|c| {
+                // Bullets that keep the ship's momentum fly relative to the ship
+                let target_velocity = if ballistics.inherit_velocity {
+                    c.relative_velocity
+                } else {
+                    c.velocity
+                };
+                let t = intercept_time(c.offset, target_velocity, BULLET_SPEED)?;
+                let aim = (
+                    c.offset.0 + target_velocity.0 * t,
+                    c.offset.1 + target_velocity.1 * t,
+                );
+                // The bullet would disappear before it gets there
+                if ballistics
+                    .range
+                    .is_some_and(|range| BULLET_SPEED * t > range)
+                {
+                    return None;
+                }
+                let aim_heading = aim.1.atan2(aim.0);
+                let turn_time = angle_between(heading, aim_heading).abs() / TURN_SPEED;
+                Some((c, aim, aim_heading, t + turn_time))
+            })
+            .min_by(|a, b| a.3.total_cmp(&b.3));
+        if let Some((contact, aim, aim_heading, _)) = shot {
+            let delta = turn_towards(&mut input, aim_heading);
+            // Fire when the bullet passes within the asteroid's outline
+            let tolerance = (contact.radius * 0.7 / length(aim).max(1.)).atan();
+            input.fire = delta.abs() < tolerance;
+        }
+
+        // Keep the speed down and leave the Danger Zone, where asteroids spawn
+        let pos = (ship.transform.pos.x, ship.transform.pos.y);
+        let in_danger_zone = pos.0 < DANGER_ZONE
+            || pos.0 > CANVAS_WIDTH as f32 - DANGER_ZONE
+            || pos.1 < DANGER_ZONE
+            || pos.1 > CANVAS_HEIGHT as f32 - DANGER_ZONE;
+        if length(velocity) > self.cruise_speed {
+            let along = dot(forward, velocity) / length(velocity);
+            if along > 0.5 {
+                input.reverse = true;
+            } else if along < -0.5 {
+                input.thrust = true;
+            }
+        } else if in_danger_zone {
+            let to_center = (
+                CANVAS_WIDTH as f32 / 2. - pos.0,
+                CANVAS_HEIGHT as f32 / 2. - pos.1,
+            );
+            let along = dot(forward, to_center) / length(to_center).max(f32::EPSILON);
+            if along > 0.5 {
+                input.thrust = true;
+            } else if along < -0.5 {
+                input.reverse = true;
+            }
+        }
+
+        input
+    }
+}
+
+/// Results of [`bench`].
+pub struct BenchReport {
+    /// Final score of every game.
+    pub scores: Vec<u32>,
+    /// Simulated seconds every game lasted.
+    pub survival_times: Vec<f64>,
+    /// Games that reached the time limit without crashing.
+    pub survived: usize,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0., 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.
+    } else {
+        sum / count as f64
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.;
+    }
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.
+    } else {
+        values[middle]
+    }
+}
+
+impl BenchReport {
+    pub fn mean_score(&self) -> f64 {
+        mean(self.scores.iter().map(|s| *s as f64))
+    }
+
+    pub fn median_score(&self) -> f64 {
+        median(self.scores.iter().map(|s| *s as f64).collect())
+    }
+
+    pub fn mean_survival_time(&self) -> f64 {
+        mean(self.survival_times.iter().copied())
+    }
+
+    pub fn median_survival_time(&self) -> f64 {
+        median(self.survival_times.clone())
+    }
+}
+
+/// Lets `pilot` play `games` games with the seeds `first_seed..first_seed + games`,
+/// each until the ship crashes or `max_time` simulated seconds have passed.
+pub fn bench(
+    pilot: &Autopilot,
+    games: u64,
+    first_seed: u64,
+    max_time: f64,
+    rules: &mut dyn Rules,
+) -> BenchReport {
+    let mut report = BenchReport {
+        scores: vec![],
+        survival_times: vec![],
+        survived: 0,
+    };
+    for seed in first_seed..first_seed + games {
+        let mut game = Game::new(seed);
+        let score = loop {
+            if game.time >= max_time {
+                report.survived += 1;
+                break game.score;
+            }
+            let input = pilot.act(&game, FIXED_DELTA_TIME);
+            game.update_with_rules(&input, FIXED_DELTA_TIME, rules);
+            let crash = game.events.iter().find_map(|e| match e {
+                GameEvent::ShipCrashed { score, .. } => Some(*score),
+                _ => None,
+            });
+            if let Some(score) = crash {
+                break score;
+            }
+        };
+        report.scores.push(score);
+        report.survival_times.push(game.time);
+    }
+    report
+}
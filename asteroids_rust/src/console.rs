@@ -0,0 +1,352 @@
+use crate::font::{self, LINE_HEIGHT};
+use crate::game::{randf32, Asteroid, Game, MIN_ASTEROID_SCALE};
+use crate::math::vec2;
+use crate::physics::AsteroidPhysics;
+use crate::powerup::PowerUpKind;
+use crate::rules::Rules;
+use crate::save::{self, QUICKSAVE_PATH};
+use crate::weapon::{Ballistics, WeaponKind};
+use crate::CANVAS_WIDTH;
+use framebrush::{Canvas, RGBu32, WHITE};
+
+/// Number of log lines visible while the console is open.
+const VISIBLE_LINES: usize = 10;
+const MAX_LOG_LINES: usize = 64;
+const BACKGROUND: RGBu32 = RGBu32::Rgb(10, 10, 25);
+const BORDER: RGBu32 = RGBu32::Rgb(86, 182, 194);
+const ERROR_PREFIX: &str = "ERROR: ";
+const ERROR_COLOR: RGBu32 = RGBu32::Rgb(230, 90, 70);
+
+const HELP: &str = "COMMANDS:
+SPAWN ASTEROID <SIZE> <X> <Y>
+GOD, TIMESCALE <N>, WAVE <N>, SEED <N>
+PHYSICS [RESTITUTION], POWERUPS, ARCADE
+GIVE <SPREAD|RAPID|SHIELD|PIERCE|SLOW|LIFE>
+WEAPON <BLASTER|LASER|MISSILE|MINE>
+SET <BULLET_COOLDOWN|ACC|SCORE> <VALUE>
+SAVE STATE [FILE], LOAD STATE [FILE], CLEAR";
+
+pub enum Command {
+    SpawnAsteroid {
+        scale: f32,
+        x: f32,
+        y: f32,
+    },
+    God,
+    TimeScale(f32),
+    /// Clears the playfield and starts a wave with the rules in use. The
+    /// standard rules spawn as many asteroids for every wave after the first,
+    /// mods can scale them.
+    Wave(u32),
+    Set {
+        variable: String,
+        value: f32,
+    },
+    Seed(u64),
+    /// Toggles asteroid physics, or turns it on with the given restitution.
+    Physics(Option<f32>),
+    /// Toggles power-up drops.
+    PowerUps,
+    /// Toggles between the bullets of this game and those of the arcade original.
+    Arcade,
+    /// Gives the first player a power-up.
+    Give(PowerUpKind),
+    /// Equips the first player with a weapon.
+    Weapon(WeaponKind),
+    SaveState(Option<String>),
+    LoadState(Option<String>),
+    Help,
+    Clear,
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&str>, name: &str) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("missing <{name}>"))?;
+    arg.parse().map_err(|_| format!("invalid <{name}> '{arg}'"))
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut args = line.split_whitespace();
+        let name = args.next().unwrap_or_default().to_ascii_lowercase();
+        let cmd = match name.as_str() {
+            "spawn" => match args.next() {
+                Some("asteroid") => Self::SpawnAsteroid {
+                    scale: parse_arg(args.next(), "size")?,
+                    x: parse_arg(args.next(), "x")?,
+                    y: parse_arg(args.next(), "y")?,
+                },
+                Some(other) => return Err(format!("can't spawn '{other}'")),
+                None => return Err("missing entity to spawn".to_string()),
+            },
+            "god" => Self::God,
+            "timescale" => Self::TimeScale(parse_arg(args.next(), "scale")?),
+            "wave" => Self::Wave(parse_arg(args.next(), "wave")?),
+            "set" => Self::Set {
+                variable: parse_arg(args.next(), "variable")?,
+                value: parse_arg(args.next(), "value")?,
+            },
+            "seed" => Self::Seed(parse_arg(args.next(), "seed")?),
+            "physics" => Self::Physics(
+                args.next()
+                    .map(|arg| parse_arg(Some(arg), "restitution"))
+                    .transpose()?,
+            ),
+            "powerups" => Self::PowerUps,
+            "arcade" => Self::Arcade,
+            "give" => {
+                let name: String = parse_arg(args.next(), "power-up")?;
+                let kind = PowerUpKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name().eq_ignore_ascii_case(&name));
+                Self::Give(kind.ok_or_else(|| format!("unknown power-up '{name}'"))?)
+            }
+            "weapon" => {
+                let name: String = parse_arg(args.next(), "weapon")?;
+                let kind = WeaponKind::ALL
+                    .into_iter()
+                    .find(|kind| kind.name().eq_ignore_ascii_case(&name));
+                Self::Weapon(kind.ok_or_else(|| format!("unknown weapon '{name}'"))?)
+            }
+            "save" | "load" => match args.next() {
+                Some("state") if name == "save" => Self::SaveState(args.next().map(str::to_string)),
+                Some("state") => Self::LoadState(args.next().map(str::to_string)),
+                _ => return Err(format!("usage: {name} state [file]")),
+            },
+            "help" => Self::Help,
+            "clear" => Self::Clear,
+            _ => return Err(format!("unknown command '{name}', try 'help'")),
+        };
+
+        match args.next() {
+            Some(extra) => Err(format!("unexpected argument '{extra}'")),
+            None => Ok(cmd),
+        }
+    }
+}
+
+/// Drop-down developer console, toggled with the grave key.
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input: String,
+    log: Vec<String>,
+    history: Vec<String>,
+    history_index: usize,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.input.clear();
+        self.history_index = self.history.len();
+    }
+
+    /// Handles a character typed while the console is open.
+    pub fn type_char(&mut self, c: char, game: &mut Game, rules: &mut dyn Rules) {
+        match c {
+            '\r' | '\n' => self.submit(game, rules),
+            '\u{8}' => {
+                self.input.pop();
+            }
+            // The toggle key also produces a character
+            '`' | '~' => (),
+            c if !c.is_control() => self.input.push(c),
+            _ => (),
+        }
+    }
+
+    /// Steps through previously submitted commands, `back` moves towards older ones.
+    pub fn recall(&mut self, back: bool) {
+        if back {
+            self.history_index = self.history_index.saturating_sub(1);
+        } else {
+            self.history_index = (self.history_index + 1).min(self.history.len());
+        }
+        self.input = self
+            .history
+            .get(self.history_index)
+            .cloned()
+            .unwrap_or_default();
+    }
+
+    /// Adds `line` to the log, lines starting with "ERROR: " are shown in red.
+    pub fn print(&mut self, line: String) {
+        self.log.extend(line.lines().map(str::to_string));
+        if self.log.len() > MAX_LOG_LINES {
+            self.log.drain(..self.log.len() - MAX_LOG_LINES);
+        }
+    }
+
+    fn submit(&mut self, game: &mut Game, rules: &mut dyn Rules) {
+        let line = std::mem::take(&mut self.input);
+        if line.trim().is_empty() {
+            return;
+        }
+        self.print(format!("> {line}"));
+        self.history.push(line.clone());
+        self.history_index = self.history.len();
+
+        match Command::parse(&line).and_then(|cmd| self.execute(cmd, game, rules)) {
+            Ok(Some(msg)) => self.print(msg),
+            Ok(None) => (),
+            Err(e) => self.print(format!("{ERROR_PREFIX}{e}")),
+        }
+    }
+
+    /// Applies `cmd` to `game` played by `rules`, returning an optional message for the log.
+    pub fn execute(
+        &mut self,
+        cmd: Command,
+        game: &mut Game,
+        rules: &mut dyn Rules,
+    ) -> Result<Option<String>, String> {
+        let msg = match cmd {
+            Command::SpawnAsteroid { scale, x, y } => {
+                if scale <= MIN_ASTEROID_SCALE {
+                    return Err(format!("size must be larger than {MIN_ASTEROID_SCALE}"));
+                }
+                let velocity =
+                    vec2(randf32(&mut game.rng), randf32(&mut game.rng)).normalise() * 25.;
+                let asteroid = Asteroid::new(&mut game.rng, vec2(x, y), scale, velocity);
+                game.asteroids.insert(asteroid);
+                format!("spawned asteroid at {x} {y}")
+            }
+            Command::God => {
+                game.god_mode = !game.god_mode;
+                format!("god mode {}", if game.god_mode { "on" } else { "off" })
+            }
+            Command::TimeScale(scale) => {
+                if scale <= 0. {
+                    return Err("timescale must be positive".to_string());
+                }
+                game.time_scale = scale;
+                format!("timescale set to {scale}")
+            }
+            Command::Wave(wave) => {
+                game.bullets.clear();
+                game.pickups.clear();
+                game.beams.clear();
+                game.start_wave_with_rules(wave, rules);
+                format!(
+                    "started wave {wave} with {} asteroids",
+                    game.asteroids.len()
+                )
+            }
+            Command::Set { variable, value } => {
+                // Variables of the first player
+                let player = &mut game.players[0];
+                match variable.to_ascii_lowercase().as_str() {
+                    "bullet_cooldown" => player.bullet_cooldown = value.max(0.) as u64,
+                    "acc" => player.ship.acc = value,
+                    "score" => {
+                        let score = value.max(0.) as u32;
+                        game.score = game.score - player.score + score;
+                        player.score = score;
+                    }
+                    _ => return Err(format!("unknown variable '{variable}'")),
+                }
+                format!("{variable} set to {value}")
+            }
+            Command::Seed(seed) => {
+                let (god_mode, friendly_fire, physics) =
+                    (game.god_mode, game.friendly_fire, game.asteroid_physics);
+                let (power_ups, ballistics) = (game.power_ups, game.ballistics);
+                let (players, lives, high_score) =
+                    (game.players.len(), game.lives, game.high_score);
+                *game = match &game.versus {
+                    Some(versus) => Game::versus(seed, players, lives, versus.rounds_to_win),
+                    None => Game::with_players(seed, players, lives),
+                };
+                game.god_mode = god_mode;
+                game.friendly_fire = friendly_fire;
+                game.asteroid_physics = physics;
+                game.power_ups = power_ups;
+                game.ballistics = ballistics;
+                game.high_score = high_score;
+                format!("restarted with seed {seed}")
+            }
+            Command::Physics(Some(restitution)) => {
+                if !(0. ..=1.).contains(&restitution) {
+                    return Err("restitution must be between 0 and 1".to_string());
+                }
+                game.asteroid_physics = Some(AsteroidPhysics { restitution });
+                format!("asteroid physics on, restitution {restitution}")
+            }
+            Command::Physics(None) => {
+                game.asteroid_physics = match game.asteroid_physics {
+                    Some(_) => None,
+                    None => Some(AsteroidPhysics::default()),
+                };
+                format!(
+                    "asteroid physics {}",
+                    if game.asteroid_physics.is_some() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                )
+            }
+            Command::PowerUps => {
+                game.power_ups = !game.power_ups;
+                format!("power-ups {}", if game.power_ups { "on" } else { "off" })
+            }
+            Command::Arcade => {
+                let arcade = game.ballistics != Ballistics::ARCADE;
+                game.ballistics = if arcade {
+                    Ballistics::ARCADE
+                } else {
+                    Ballistics::default()
+                };
+                format!("arcade bullets {}", if arcade { "on" } else { "off" })
+            }
+            Command::Give(kind) => {
+                game.players[0].collect(kind, game.time);
+                format!("gave {}", kind.name())
+            }
+            Command::Weapon(kind) => {
+                game.players[0].weapon = kind;
+                format!("equipped {}", kind.name())
+            }
+            Command::SaveState(path) => {
+                let path = path.as_deref().unwrap_or(QUICKSAVE_PATH);
+                save::save(game, path).map_err(|e| e.to_string())?;
+                format!("state saved to {path}")
+            }
+            Command::LoadState(path) => {
+                let path = path.as_deref().unwrap_or(QUICKSAVE_PATH);
+                *game = save::load(path).map_err(|e| e.to_string())?;
+                format!("state loaded from {path}")
+            }
+            Command::Help => HELP.to_string(),
+            Command::Clear => {
+                self.log.clear();
+                return Ok(None);
+            }
+        };
+        Ok(Some(msg))
+    }
+
+    pub fn draw(&self, canvas: &mut Canvas<'_, u32>) {
+        let height = (VISIBLE_LINES + 1) * LINE_HEIGHT + 4;
+        canvas.rect(0, 0, CANVAS_WIDTH, height, &BACKGROUND);
+        canvas.line(
+            0,
+            height as i32,
+            CANVAS_WIDTH as i32 - 1,
+            height as i32,
+            &BORDER,
+        );
+
+        let visible = &self.log[self.log.len().saturating_sub(VISIBLE_LINES)..];
+        for (i, line) in visible.iter().enumerate() {
+            let (line, color) = match line.strip_prefix(ERROR_PREFIX) {
+                Some(error) => (error, &ERROR_COLOR),
+                None => (line.as_str(), &WHITE),
+            };
+            font::draw_text(canvas, 2, 2 + (i * LINE_HEIGHT) as i32, line, color);
+        }
+
+        let prompt_y = 2 + (VISIBLE_LINES * LINE_HEIGHT) as i32;
+        font::draw_text(canvas, 2, prompt_y, &format!("> {}_", self.input), &BORDER);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::font::{self, LINE_HEIGHT};
+use framebrush::{Canvas, RGBu32, WHITE};
+
+/// Number of frames kept for the frame time graph.
+pub const FRAME_HISTORY: usize = 96;
+/// Height of the frame time graph in canvas pixels, one pixel per millisecond.
+const GRAPH_HEIGHT: usize = 40;
+const GRAPH_BG: RGBu32 = RGBu32::Rgb(20, 20, 30);
+const GRAPH_BAR: RGBu32 = RGBu32::Rgb(120, 200, 120);
+const GRAPH_SLOW_BAR: RGBu32 = RGBu32::Rgb(220, 80, 60);
+const GRAPH_TARGET: RGBu32 = RGBu32::Rgb(90, 90, 140);
+/// 60 FPS, drawn as a reference line on the graph.
+const TARGET_FRAME_MS: f32 = 1000. / 60.;
+
+pub const STATS_COLOR: RGBu32 = WHITE;
+pub const VELOCITY_COLOR: RGBu32 = RGBu32::Rgb(200, 120, 255);
+pub const BOUNDS_COLOR: RGBu32 = RGBu32::Rgb(0, 110, 60);
+pub const WRAP_COUNT_COLOR: RGBu32 = RGBu32::Rgb(160, 160, 160);
+pub const GRID_COLOR: RGBu32 = RGBu32::Rgb(50, 50, 90);
+pub const GRID_COUNT_COLOR: RGBu32 = RGBu32::Rgb(120, 120, 200);
+/// Velocity vectors are drawn as the distance travelled in this many seconds.
+pub const VELOCITY_VECTOR_SECONDS: f32 = 0.25;
+
+/// Developer overlay state, every view can be toggled on its own.
+pub struct DebugOverlay {
+    pub hitbox: bool,
+    pub stats: bool,
+    pub frame_graph: bool,
+    pub velocities: bool,
+    pub bounds: bool,
+    pub wrap_counts: bool,
+    /// Collision grid cells with the number of asteroids overlapping each.
+    pub grid: bool,
+    frame_times: [f32; FRAME_HISTORY], // milliseconds
+    frame_index: usize,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self {
+            hitbox: false,
+            stats: false,
+            frame_graph: false,
+            velocities: false,
+            bounds: false,
+            wrap_counts: false,
+            grid: false,
+            frame_times: [0.; FRAME_HISTORY],
+            frame_index: 0,
+        }
+    }
+}
+
+impl DebugOverlay {
+    pub fn record_frame(&mut self, frame_time: f32) {
+        self.frame_times[self.frame_index] = frame_time * 1000.;
+        self.frame_index = (self.frame_index + 1) % FRAME_HISTORY;
+    }
+
+    /// Average frame time in milliseconds over the recorded history.
+    pub fn average_frame_time(&self) -> f32 {
+        let (sum, count) = self
+            .frame_times
+            .iter()
+            .filter(|t| **t > 0.)
+            .fold((0., 0), |(sum, count), t| (sum + t, count + 1));
+        if count == 0 {
+            0.
+        } else {
+            sum / count as f32
+        }
+    }
+
+    /// Draws the FPS counter and the frame time graph with its top left corner at (`x`, `y`).
+    pub fn draw_frame_graph(&self, canvas: &mut Canvas<'_, u32>, x: i32, y: i32) {
+        let avg = self.average_frame_time();
+        let fps = if avg > 0. { 1000. / avg } else { 0. };
+        font::draw_text(
+            canvas,
+            x,
+            y,
+            &format!("FPS {fps:.0} {avg:.1}MS"),
+            &STATS_COLOR,
+        );
+
+        let top = y + LINE_HEIGHT as i32;
+        let bottom = top + GRAPH_HEIGHT as i32 - 1;
+        canvas.rect(x, top, FRAME_HISTORY, GRAPH_HEIGHT, &GRAPH_BG);
+
+        // Oldest frame on the left, newest on the right
+        for i in 0..FRAME_HISTORY {
+            let ms = self.frame_times[(self.frame_index + i) % FRAME_HISTORY];
+            if ms <= 0. {
+                continue;
+            }
+            let height = (ms as i32).clamp(1, GRAPH_HEIGHT as i32);
+            let color = if ms > TARGET_FRAME_MS * 2. {
+                &GRAPH_SLOW_BAR
+            } else {
+                &GRAPH_BAR
+            };
+            canvas.line(
+                x + i as i32,
+                bottom,
+                x + i as i32,
+                bottom - height + 1,
+                color,
+            );
+        }
+
+        let target = bottom - TARGET_FRAME_MS as i32;
+        canvas.line(
+            x,
+            target,
+            x + FRAME_HISTORY as i32 - 1,
+            target,
+            &GRAPH_TARGET,
+        );
+    }
+
+    /// Draws one line of text per entry of `lines` starting at (`x`, `y`).
+    pub fn draw_stats(&self, canvas: &mut Canvas<'_, u32>, x: i32, y: i32, lines: &[String]) {
+        for (i, line) in lines.iter().enumerate() {
+            font::draw_text(canvas, x, y + (i * LINE_HEIGHT) as i32, line, &STATS_COLOR);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Gym style environment for training agents against the game.
+//!
+//! ```ignore
+//! let mut env = Env::new(EnvConfig::default());
+//! let mut observation = env.reset(42);
+//! loop {
+//!     let action = Input::from_bits(agent.act(&observation.features));
+//!     let (next, reward, done, info) = env.step(&action);
+//!     // ...
+//!     if done {
+//!         observation = env.reset(info.steps);
+//!     } else {
+//!         observation = next;
+//!     }
+//! }
+//! ```
+
+use crate::debug::DebugOverlay;
+use crate::game::{Game, GameEvent, Input, DEFAULT_BULLET_COOLDOWN, FIXED_DELTA_TIME};
+use crate::headless::FrameBuffer;
+use crate::math::wrap_delta;
+use crate::physics::AsteroidPhysics;
+use crate::spectate::Broadcaster;
+use crate::weapon::Ballistics;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+
+/// Number of asteroids described by the feature vector.
+pub const NEAREST_ASTEROIDS: usize = 8;
+pub const SHIP_FEATURES: usize = 8;
+pub const ASTEROID_FEATURES: usize = 6;
+pub const FEATURE_COUNT: usize = SHIP_FEATURES + NEAREST_ASTEROIDS * ASTEROID_FEATURES;
+/// Velocities are divided by this to keep features roughly within [-1, 1].
+const VELOCITY_NORM: f32 = 100.;
+/// Largest scale a spawned asteroid can have.
+const SCALE_NORM: f32 = 16.;
+
+/// Actions are the same inputs a human player gives.
+pub type Action = Input;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ObservationKind {
+    Features,
+    Pixels,
+    Both,
+}
+
+#[derive(Clone)]
+pub struct EnvConfig {
+    /// Simulated seconds per game update.
+    pub delta_time: f32,
+    /// Game updates per step, the action is repeated for all of them.
+    pub frame_skip: u32,
+    /// Ends (truncates) the episode after this many steps.
+    pub max_steps: Option<u64>,
+    pub observation: ObservationKind,
+    pub reward_per_asteroid: f32,
+    /// Reward for crashing, usually negative.
+    pub crash_reward: f32,
+    /// Lets the asteroids of new episodes bounce off each other.
+    pub asteroid_physics: Option<AsteroidPhysics>,
+    /// Lets shot asteroids of new episodes drop power-ups.
+    pub power_ups: bool,
+    /// How bullets of new episodes fly.
+    pub ballistics: Ballistics,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            delta_time: FIXED_DELTA_TIME,
+            frame_skip: 1,
+            max_steps: None,
+            observation: ObservationKind::Features,
+            reward_per_asteroid: 1.,
+            crash_reward: -10.,
+            asteroid_physics: None,
+            power_ups: false,
+            ballistics: Ballistics::default(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Observation {
+    /// [`FEATURE_COUNT`] values laid out as described in [`Env::write_features`],
+    /// empty unless requested by [`EnvConfig::observation`].
+    pub features: Vec<f32>,
+    /// `CANVAS_WIDTH * CANVAS_HEIGHT` row major 0RGB pixels, empty unless requested.
+    pub pixels: Vec<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Info {
+    pub score: u32,
+    pub wave: u32,
+    pub steps: u64,
+    pub asteroids: usize,
+    /// The episode ended because the ship crashed.
+    pub crashed: bool,
+    /// The episode ended because `max_steps` was reached.
+    pub truncated: bool,
+}
+
+pub struct Env {
+    game: Game,
+    config: EnvConfig,
+    frame_buffer: FrameBuffer,
+    debug_overlay: DebugOverlay,
+    steps: u64,
+    done: bool,
+    last_info: Info,
+    broadcaster: Option<Broadcaster>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        Self {
+            game: Game::new(0),
+            config,
+            frame_buffer: FrameBuffer::new(1),
+            debug_overlay: DebugOverlay::default(),
+            steps: 0,
+            done: false,
+            last_info: Info::default(),
+            broadcaster: None,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// Streams every episode to viewers through `broadcaster`, e.g. to watch
+    /// an agent while it trains, see [`crate::spectate`].
+    pub fn spectate(&mut self, broadcaster: Option<Broadcaster>) {
+        self.broadcaster = broadcaster;
+        if let Some(broadcaster) = &mut self.broadcaster {
+            broadcaster.snapshot(&self.game);
+        }
+    }
+
+    /// Starts a new episode.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        let mut game = Game::new(seed);
+        game.asteroid_physics = self.config.asteroid_physics;
+        game.power_ups = self.config.power_ups;
+        game.ballistics = self.config.ballistics;
+        self.reset_to(game)
+    }
+
+    /// Starts a new episode from `game`, e.g. a save file or the start of a replay.
+    pub fn reset_to(&mut self, game: Game) -> Observation {
+        self.game = game;
+        if let Some(broadcaster) = &mut self.broadcaster {
+            broadcaster.snapshot(&self.game);
+        }
+        self.steps = 0;
+        self.done = false;
+        self.last_info = self.info(false, false);
+        self.observe()
+    }
+
+    /// Applies `action` for `frame_skip` updates. Returns the observation after
+    /// them, the reward collected, whether the episode is over and extra info.
+    /// Stepping a finished episode does nothing until [`Env::reset`] is called.
+    pub fn step(&mut self, action: &Action) -> (Observation, f32, bool, Info) {
+        self.step_with_delta(action, self.config.delta_time)
+    }
+
+    /// Like [`Env::step`] but every update simulates `delta_time` seconds instead of
+    /// [`EnvConfig::delta_time`], e.g. to follow the ticks of a replay.
+    pub fn step_with_delta(
+        &mut self,
+        action: &Action,
+        delta_time: f32,
+    ) -> (Observation, f32, bool, Info) {
+        if self.done {
+            return (self.observe(), 0., true, self.last_info);
+        }
+
+        let mut reward = 0.;
+        let mut crashed = false;
+        let mut crash_score = 0;
+        for _ in 0..self.config.frame_skip.max(1) {
+            self.game.update(action, delta_time);
+            if let Some(broadcaster) = &mut self.broadcaster {
+                broadcaster.update(&self.game, std::slice::from_ref(action), delta_time);
+            }
+            for event in &self.game.events {
+                match event {
+                    GameEvent::AsteroidDestroyed { .. } => {
+                        reward += self.config.reward_per_asteroid
+                    }
+                    GameEvent::LifeLost { .. } => reward += self.config.crash_reward,
+                    GameEvent::RoundOver { .. }
+                    | GameEvent::MatchWon { .. }
+                    | GameEvent::PowerUpCollected { .. } => (),
+                    GameEvent::ShipCrashed { score, .. } => {
+                        reward += self.config.crash_reward;
+                        crashed = true;
+                        crash_score = *score;
+                    }
+                }
+            }
+            if crashed {
+                break;
+            }
+        }
+        self.steps += 1;
+
+        let truncated = !crashed && self.config.max_steps.is_some_and(|max| self.steps >= max);
+        self.done = crashed || truncated;
+        let mut info = self.info(crashed, truncated);
+        if crashed {
+            // The game already reset itself, report the score of the finished run
+            info.score = crash_score;
+        }
+        self.last_info = info;
+
+        (self.observe(), reward, self.done, info)
+    }
+
+    fn info(&self, crashed: bool, truncated: bool) -> Info {
+        Info {
+            score: self.game.score,
+            wave: self.game.wave,
+            steps: self.steps,
+            asteroids: self.game.asteroids.len(),
+            crashed,
+            truncated,
+        }
+    }
+
+    pub fn observe(&mut self) -> Observation {
+        let mut observation = Observation::default();
+        if self.config.observation != ObservationKind::Pixels {
+            observation.features = vec![0.; FEATURE_COUNT];
+            self.write_features(&mut observation.features);
+        }
+        if self.config.observation != ObservationKind::Features {
+            observation.pixels = self.render().to_vec();
+        }
+        observation
+    }
+
+    /// Writes the feature vector into `out`, which must hold [`FEATURE_COUNT`] values.
+    ///
+    /// The first [`SHIP_FEATURES`] values describe the ship: x and y position
+    /// normalised to [0, 1], x and y velocity, sine and cosine of the rotation,
+    /// the remaining weapon cooldown as a fraction of the current cooldown and
+    /// the current cooldown relative to the starting one.
+    ///
+    /// They are followed by [`ASTEROID_FEATURES`] values for each of the
+    /// [`NEAREST_ASTEROIDS`] closest asteroids, nearest first: 1 if the slot is
+    /// used and 0 otherwise, the x and y offset from the ship (taking the
+    /// screen wrap into account) normalised to [-1, 1], x and y velocity and
+    /// the scale. Unused slots are all zeroes.
+    pub fn write_features(&self, out: &mut [f32]) {
+        assert_eq!(
+            out.len(),
+            FEATURE_COUNT,
+            "feature buffer has the wrong length"
+        );
+        let game = &self.game;
+        let player = &game.players[0];
+        let ship = &player.ship;
+        let (w, h) = (CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32);
+
+        let cooldown = player.bullet_cooldown as f64 / 1000.;
+        let remaining = if cooldown > 0. {
+            ((cooldown - (game.time - player.last_bullet)) / cooldown).max(0.)
+        } else {
+            0.
+        };
+        let (sin, cos) = ship.transform.rot.sin_cos();
+        out[..SHIP_FEATURES].copy_from_slice(&[
+            ship.transform.pos.x / w,
+            ship.transform.pos.y / h,
+            ship.velocity.x / VELOCITY_NORM,
+            ship.velocity.y / VELOCITY_NORM,
+            sin,
+            cos,
+            remaining as f32,
+            player.bullet_cooldown as f32 / DEFAULT_BULLET_COOLDOWN as f32,
+        ]);
+
+        let offset = |i: usize| {
+            let pos = &game.asteroids[i].transform.pos;
+            (
+                wrap_delta(pos.x - ship.transform.pos.x, w),
+                wrap_delta(pos.y - ship.transform.pos.y, h),
+            )
+        };
+        let mut nearest: Vec<(f32, usize)> = (0..game.asteroids.len())
+            .map(|i| {
+                let (dx, dy) = offset(i);
+                (dx * dx + dy * dy, i)
+            })
+            .collect();
+        nearest.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let slots = out[SHIP_FEATURES..].chunks_exact_mut(ASTEROID_FEATURES);
+        for (slot, nearest) in slots.zip(nearest.iter().map(Some).chain(std::iter::repeat(None))) {
+            match nearest {
+                Some(&(_, i)) => {
+                    let asteroid = &game.asteroids[i];
+                    let (dx, dy) = offset(i);
+                    slot.copy_from_slice(&[
+                        1.,
+                        dx / (w / 2.),
+                        dy / (h / 2.),
+                        asteroid.velocity.x / VELOCITY_NORM,
+                        asteroid.velocity.y / VELOCITY_NORM,
+                        asteroid.transform.scale / SCALE_NORM,
+                    ]);
+                }
+                None => slot.fill(0.),
+            }
+        }
+    }
+
+    /// Renders the current state at the canvas resolution.
+    pub fn render(&mut self) -> &[u32] {
+        self.frame_buffer.render(&self.game, &self.debug_overlay);
+        &self.frame_buffer.pixels
+    }
+}
@@ -0,0 +1,1480 @@
+use crate::grid::{Bounds, Grid};
+use crate::math::{self, vec2, wrap_delta, Contact, Polygon, Transform, Vec2};
+use crate::physics::{self, AsteroidPhysics};
+use crate::pool::{Handle, Pool};
+use crate::powerup::{self, Effects, PowerUp, PowerUpKind};
+use crate::rules::{DefaultRules, Rules};
+use crate::shape;
+use crate::weapon::{self, Ballistics, Lifetime, WeaponKind};
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+pub const DANGER_ZONE: f32 = (1. / 20.) * (CANVAS_HEIGHT as f32);
+pub const DEFAULT_ACCELERATION: f32 = 25.;
+pub const DEFAULT_BULLET_COOLDOWN: u64 = 1100;
+pub const BULLET_SPEED: f32 = 155.;
+/// Asteroids at or below this scale are destroyed instead of split.
+pub const MIN_ASTEROID_SCALE: f32 = 3.;
+/// Fastest an asteroid spins when it's created, in radians per second.
+pub const MAX_ASTEROID_SPIN: f32 = 1.5;
+/// Asteroids spawned every time the playfield is cleared.
+pub const WAVE_ASTEROIDS: u32 = 4;
+/// Delta time used when the game isn't driven by a real clock, e.g. in headless mode.
+pub const FIXED_DELTA_TIME: f32 = 1. / 60.;
+/// Lives of every player in a multiplayer game unless configured otherwise.
+pub const DEFAULT_LIVES: u32 = 3;
+/// Seconds a respawned ship can't crash.
+pub const RESPAWN_INVULNERABILITY: f64 = 2.;
+/// Horizontal distance between the ships at the start of a multiplayer game.
+const SPAWN_SPACING: f32 = 80.;
+/// Round wins needed to win a versus match unless configured otherwise.
+pub const DEFAULT_ROUNDS_TO_WIN: u32 = 3;
+/// Number of kills the kill feed remembers.
+pub const KILL_FEED_LENGTH: usize = 5;
+/// Seconds the shield lasts on a full charge.
+pub const SHIELD_ENERGY: f32 = 3.;
+/// Energy the shield regains per second while it's down.
+pub const SHIELD_RECHARGE: f32 = 0.5;
+/// Energy needed to raise the shield again after it went down.
+pub const SHIELD_MIN_ENERGY: f32 = 0.5;
+/// Radius of the shield relative to the ship's scale.
+pub const SHIELD_RADIUS: f32 = 1.5;
+/// Asteroids and bullets the pools of a new game hold before they allocate.
+pub const ASTEROID_CAPACITY: usize = 256;
+pub const BULLET_CAPACITY: usize = 64;
+pub const POWER_UP_CAPACITY: usize = 16;
+pub const BEAM_CAPACITY: usize = 8;
+/// Events and asteroid pieces per update the game has room for before it allocates.
+const EVENT_CAPACITY: usize = 16;
+const PIECE_CAPACITY: usize = 16;
+/// Vertices of the ship's [`Transform`].
+pub const SHIP_VERTICES: usize = 3;
+/// The standard ship, kept in the transform when the installed outline isn't a triangle.
+const STANDARD_SHIP: [[f32; 2]; SHIP_VERTICES] = [
+    [-FRAC_1_SQRT_2, -FRAC_1_SQRT_2],
+    [FRAC_1_SQRT_2, -FRAC_1_SQRT_2],
+    [0., 1.],
+];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ship {
+    /// Holds the installed outline when it's a triangle, see [`Ship::outline`].
+    pub transform: Transform<SHIP_VERTICES>,
+    pub velocity: Vec2,
+    pub acc: f32,
+    pub hitbox: [Vec2; 4],
+}
+
+impl Ship {
+    fn new(pos: Vec2) -> Self {
+        let outline = &shape::shapes().ship.outline.vertices;
+        let outline = match outline.len() {
+            SHIP_VERTICES => outline.as_slice(),
+            _ => &STANDARD_SHIP,
+        };
+        let vertices: [Vec2; SHIP_VERTICES] =
+            std::array::from_fn(|i| vec2(outline[i][0], outline[i][1]));
+        let mut ship = Ship {
+            transform: Transform {
+                pos,
+                vertices: vertices.clone(),
+                scale: 10.,
+                rot: 0.,
+                transform: vertices,
+            },
+            velocity: vec2(0., 0.),
+            acc: DEFAULT_ACCELERATION,
+            hitbox: [vec2(0., 0.), vec2(0., 0.), vec2(0., 0.), vec2(0., 0.)],
+        };
+        ship.update(0.);
+        ship.transform.apply();
+        ship
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.transform.pos.x += self.velocity.x * delta_time;
+        self.transform.pos.y += self.velocity.y * delta_time;
+
+        for (v, &[x, y]) in self.hitbox.iter_mut().zip(&shape::shapes().ship.hitbox) {
+            *v = vec2(x, y) * self.transform.scale;
+            v.rotate_mut(self.transform.rot);
+            *v += &self.transform.pos;
+        }
+    }
+
+    /// The installed outline around the ship. Triangles come straight from
+    /// the transform, other outlines are transformed on every call.
+    pub fn outline(&self) -> Cow<'_, [Vec2]> {
+        let outline = &shape::shapes().ship.outline;
+        if outline.vertices.len() == SHIP_VERTICES {
+            return Cow::Borrowed(self.transform.transformed());
+        }
+        let t = &self.transform;
+        let points = outline.points().map(|v| {
+            let mut v = v.rotate(t.rot) * t.scale;
+            v += &t.pos;
+            v
+        });
+        Cow::Owned(points.collect())
+    }
+
+    /// Bounding box of the hitbox and the ship's position.
+    pub fn bounds(&self) -> Bounds {
+        let Vec2 { x, y } = self.transform.pos;
+        let (mut left, mut right, mut top, mut bottom) = (x, x, y, y);
+        for &Vec2 { x, y } in self.hitbox.iter() {
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+        (left, right, top, bottom)
+    }
+
+    pub fn shield_radius(&self) -> f32 {
+        self.transform.scale * SHIELD_RADIUS
+    }
+
+    /// Bounding box of the shield around the ship.
+    pub fn shield_bounds(&self) -> Bounds {
+        let (Vec2 { x, y }, r) = (&self.transform.pos, self.shield_radius());
+        (x - r, x + r, y - r, y + r)
+    }
+
+    /// Bounding box of the hitbox alone.
+    pub fn hitbox_bounds(&self) -> Bounds {
+        let (mut left, mut right, mut top, mut bottom) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+        for &Vec2 { x, y } in self.hitbox.iter() {
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+        (left, right, top, bottom)
+    }
+
+    /// Whether the point is inside the bounding box of the hitbox.
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let (left, right, top, bottom) = self.hitbox_bounds();
+
+        (left..right).contains(&x) && (top..bottom).contains(&y)
+    }
+}
+
+/// A shield around the ship that runs on energy, see [`Input::shield`].
+/// Asteroids bounce off it and the ship can't crash while it's up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shield {
+    /// Seconds the shield can stay up, at most [`SHIELD_ENERGY`].
+    pub energy: f32,
+    pub up: bool,
+}
+
+impl Default for Shield {
+    fn default() -> Self {
+        Self {
+            energy: SHIELD_ENERGY,
+            up: false,
+        }
+    }
+}
+
+impl Shield {
+    /// Keeps the shield up while `held` and there is energy left, and
+    /// recharges it while it's down.
+    fn update(&mut self, held: bool, delta_time: f32) {
+        let needed = if self.up { 0. } else { SHIELD_MIN_ENERGY };
+        self.up = held && self.energy > needed;
+        if self.up {
+            self.energy = (self.energy - delta_time).max(0.);
+        } else {
+            self.energy = (self.energy + SHIELD_RECHARGE * delta_time).min(SHIELD_ENERGY);
+        }
+    }
+}
+
+/// Where the ship of player `index` out of `count` starts, next to each other around the center.
+pub fn spawn_position(index: usize, count: usize) -> Vec2 {
+    let offset = index as f32 - (count - 1) as f32 / 2.;
+    vec2(
+        (CANVAS_WIDTH / 2) as f32 + offset * SPAWN_SPACING,
+        (CANVAS_HEIGHT / 2) as f32,
+    )
+}
+
+/// A ship and everything that belongs to the person flying it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub ship: Ship,
+    pub score: u32,
+    /// Lives left including the current one, the player is out at 0.
+    pub lives: u32,
+    pub bullet_cooldown: u64, // milliseconds
+    pub last_bullet: f64,
+    /// The ship can't crash until this time, see [`RESPAWN_INVULNERABILITY`].
+    pub invulnerable_until: f64,
+    /// Ships of other players this player shot down.
+    pub kills: u32,
+    /// Rounds won in a versus match.
+    pub round_wins: u32,
+    /// Timed power-ups, see [`Game::power_ups`].
+    #[serde(default)]
+    pub effects: Effects,
+    #[serde(default)]
+    pub shield: Shield,
+    /// The equipped weapon, see [`Input::switch_weapon`].
+    #[serde(default)]
+    pub weapon: WeaponKind,
+}
+
+impl Player {
+    fn new(pos: Vec2, lives: u32) -> Self {
+        Self {
+            ship: Ship::new(pos),
+            score: 0,
+            lives,
+            bullet_cooldown: DEFAULT_BULLET_COOLDOWN,
+            last_bullet: -(DEFAULT_BULLET_COOLDOWN as f64 / 1000.),
+            invulnerable_until: 0.,
+            kills: 0,
+            round_wins: 0,
+            effects: Effects::default(),
+            shield: Shield::default(),
+            weapon: WeaponKind::default(),
+        }
+    }
+
+    /// Whether the player still takes part in the game.
+    pub fn is_active(&self) -> bool {
+        self.lives > 0
+    }
+
+    /// Whether the ship is protected by its shield or the shield power-up.
+    pub fn shielded(&self, time: f64) -> bool {
+        self.shield.up || self.effects.active(PowerUpKind::Shield, time)
+    }
+
+    /// Applies the power-up `kind` collected at `time`.
+    pub fn collect(&mut self, kind: PowerUpKind, time: f64) {
+        match kind {
+            PowerUpKind::ExtraLife => self.lives = (self.lives + 1).min(powerup::MAX_LIVES),
+            kind => self.effects.add(kind, time),
+        }
+    }
+
+    /// Puts the ship back at `pos`, standing still and facing up.
+    fn respawn(&mut self, pos: Vec2) {
+        self.ship.transform.pos = pos;
+        self.ship.acc = DEFAULT_ACCELERATION;
+        self.ship.velocity = vec2(0., 0.);
+        self.ship.transform.rot = FRAC_PI_2 * 2.;
+        self.shield = Shield::default();
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Asteroid {
+    pub transform: Polygon,
+    pub velocity: Vec2,
+    /// Radians per second `transform.rot` turns by, collisions with other
+    /// asteroids change it, see [`Game::asteroid_physics`].
+    #[serde(default)]
+    pub spin: f32,
+}
+
+pub fn randf32(rng: &mut impl Rng) -> f32 {
+    rng.gen::<f32>() * 2. - 1.
+}
+
+impl Asteroid {
+    /// Creates an asteroid with a random outline for its size and a random spin.
+    pub fn new(rng: &mut impl Rng, pos: Vec2, scale: f32, velocity: Vec2) -> Self {
+        let mut transform = shape::asteroid_outline(rng, scale);
+        transform.pos = pos;
+        transform.scale = scale;
+        transform.apply();
+        Self {
+            transform,
+            velocity,
+            spin: randf32(rng) * MAX_ASTEROID_SPIN,
+        }
+    }
+
+    /// Creates an asteroid inside the Danger Zone that doesn't overlap the ship of an active player.
+    pub fn random(rng: &mut impl Rng, players: &[Player]) -> Self {
+        loop {
+            let velocity = vec2(randf32(rng) * 25., randf32(rng) * 25.);
+            let pos = Vec2 {
+                x: if velocity.x >= 0. {
+                    rng.gen::<f32>() * DANGER_ZONE
+                } else {
+                    CANVAS_WIDTH as f32 - (rng.gen::<f32>() * DANGER_ZONE)
+                },
+                y: if velocity.y >= 0. {
+                    rng.gen::<f32>() * DANGER_ZONE
+                } else {
+                    CANVAS_HEIGHT as f32 - (rng.gen::<f32>() * DANGER_ZONE)
+                },
+            };
+            let scale = 8. * (rng.gen::<f32>() + 1.);
+            let res = Self::new(rng, pos, scale, velocity);
+
+            let inside_ship =
+                players
+                    .iter()
+                    .filter(|p| p.is_active())
+                    .any(|Player { ship, .. }| {
+                        ship.hitbox.iter().any(|Vec2 { x, y }| res.contains(*x, *y))
+                            || res.contains(ship.transform.pos.x, ship.transform.pos.y)
+                    });
+            if !inside_ship {
+                return res;
+            }
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let (left, right, top, bottom) = self.bounds();
+
+        (left..right).contains(&x) && (top..bottom).contains(&y)
+    }
+
+    /// Largest distance of the outline from the centre, see [`shape::generate`].
+    pub fn radius(&self) -> f32 {
+        self.transform.scale
+    }
+
+    /// Mass of the disc of [`Asteroid::radius`] at unit density.
+    pub fn mass(&self) -> f32 {
+        self.transform.scale * self.transform.scale
+    }
+
+    pub fn moment_of_inertia(&self) -> f32 {
+        self.mass() * self.radius() * self.radius() / 2.
+    }
+
+    /// Axis aligned bounding box of the transformed vertices as (left, right, top, bottom).
+    pub fn bounds(&self) -> (f32, f32, f32, f32) {
+        let points = self.transform.transformed();
+        let (mut left, mut right, mut top, mut bottom): (f32, f32, f32, f32) =
+            (points[0].x, points[0].x, points[0].y, points[0].y);
+
+        for &Vec2 { x, y } in points {
+            left = left.min(x);
+            right = right.max(x);
+            top = top.min(y);
+            bottom = bottom.max(y);
+        }
+
+        (left, right, top, bottom)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bullet {
+    pub pos: Vec2,
+    pub dir: Vec2,
+    pub wrap_count: u8,
+    /// Index of the player that fired the bullet.
+    pub owner: usize,
+    /// Asteroids the bullet still flies through, see [`PowerUpKind::Piercing`].
+    #[serde(default)]
+    pub pierce: u8,
+    /// Game time the bullet last flew through an asteroid.
+    #[serde(default)]
+    pub last_hit: Option<f64>,
+    /// The weapon that fired the bullet, which decides how it flies and how long it lasts.
+    #[serde(default)]
+    pub weapon: WeaponKind,
+    /// Distance flown so far at the weapon's speed, not counting [`Bullet::inherited`].
+    #[serde(default)]
+    pub traveled: f32,
+    /// Seconds since the bullet was fired.
+    #[serde(default)]
+    pub age: f32,
+    /// Velocity of the ship that fired the bullet, see [`Ballistics::inherit_velocity`].
+    #[serde(default)]
+    pub inherited: Vec2,
+}
+
+impl Bullet {
+    pub fn new(pos: Vec2, dir: Vec2, owner: usize) -> Self {
+        Self {
+            pos,
+            dir,
+            wrap_count: 0,
+            owner,
+            pierce: 0,
+            last_hit: None,
+            weapon: WeaponKind::default(),
+            traveled: 0.,
+            age: 0.,
+            inherited: vec2(0., 0.),
+        }
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        // Bullets fly along (dir.y, dir.x)
+        vec2(self.dir.y, self.dir.x) * self.weapon.stats().speed + &self.inherited
+    }
+
+    /// Whether the bullet is still within the lifetime of its weapon, with
+    /// screen wraps replaced by `range` if there is one.
+    fn alive(&self, range: Option<f32>) -> bool {
+        match (self.weapon.stats().lifetime, range) {
+            (Lifetime::Wraps(_), Some(range)) => self.traveled < range,
+            (Lifetime::Wraps(wraps), None) => self.wrap_count < wraps,
+            (Lifetime::Distance(distance), _) => self.traveled < distance,
+            (Lifetime::Time(seconds), _) => self.age < seconds,
+        }
+    }
+
+    /// Turns a missile towards the nearest asteroid, at most by
+    /// [`weapon::MISSILE_TURN_RATE`] per second.
+    fn steer(&mut self, asteroids: &[Asteroid], delta_time: f32) {
+        let (w, h) = (CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32);
+        let target = asteroids
+            .iter()
+            .map(|a| {
+                vec2(
+                    wrap_delta(a.transform.pos.x - self.pos.x, w),
+                    wrap_delta(a.transform.pos.y - self.pos.y, h),
+                )
+            })
+            .min_by(|a, b| a.dot(a).total_cmp(&b.dot(b)));
+        let Some(target) = target else {
+            return;
+        };
+        // Bullets fly along (dir.y, dir.x)
+        let heading = vec2(self.dir.y, self.dir.x);
+        let angle = heading.cross(&target).atan2(heading.dot(&target));
+        let max_turn = weapon::MISSILE_TURN_RATE * delta_time;
+        let heading = heading.rotate(angle.clamp(-max_turn, max_turn));
+        self.dir = vec2(heading.y, heading.x);
+    }
+
+    /// Whether the bullet can hit an asteroid at `time`.
+    fn can_hit(&self, time: f64) -> bool {
+        self.last_hit
+            .is_none_or(|last| time - last >= powerup::PIERCE_DELAY)
+    }
+}
+
+/// A laser shot as it is drawn, see [`WeaponKind::Laser`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Beam {
+    pub from: Vec2,
+    /// Where the ray hit something or ran out.
+    pub to: Vec2,
+    /// Index of the player that fired the laser.
+    pub owner: usize,
+    /// Game time the beam disappears at.
+    pub expires: f64,
+}
+
+/// A laser fired in this update, resolved before the asteroids move.
+#[derive(Clone)]
+struct Laser {
+    origin: Vec2,
+    /// Unit direction of the ray.
+    dir: Vec2,
+    owner: usize,
+}
+
+/// Player input for a single update.
+#[derive(Default, Clone, Copy)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub thrust: bool,
+    pub reverse: bool,
+    /// Only true on the update the fire key was pressed.
+    pub fire: bool,
+    /// Held to keep the ship's [`Shield`] up.
+    pub shield: bool,
+    /// Only true on the update the key to equip the next [`WeaponKind`] was pressed.
+    pub switch_weapon: bool,
+}
+
+impl Input {
+    pub const LEFT: u8 = 1 << 0;
+    pub const RIGHT: u8 = 1 << 1;
+    pub const THRUST: u8 = 1 << 2;
+    pub const REVERSE: u8 = 1 << 3;
+    pub const FIRE: u8 = 1 << 4;
+    pub const SHIELD: u8 = 1 << 5;
+    pub const SWITCH_WEAPON: u8 = 1 << 6;
+
+    /// Packs the input into a bitmask of the constants above.
+    pub fn bits(&self) -> u8 {
+        let mut bits = 0;
+        for (pressed, bit) in [
+            (self.left, Self::LEFT),
+            (self.right, Self::RIGHT),
+            (self.thrust, Self::THRUST),
+            (self.reverse, Self::REVERSE),
+            (self.fire, Self::FIRE),
+            (self.shield, Self::SHIELD),
+            (self.switch_weapon, Self::SWITCH_WEAPON),
+        ] {
+            if pressed {
+                bits |= bit;
+            }
+        }
+        bits
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            left: bits & Self::LEFT != 0,
+            right: bits & Self::RIGHT != 0,
+            thrust: bits & Self::THRUST != 0,
+            reverse: bits & Self::REVERSE != 0,
+            fire: bits & Self::FIRE != 0,
+            shield: bits & Self::SHIELD != 0,
+            switch_weapon: bits & Self::SWITCH_WEAPON != 0,
+        }
+    }
+}
+
+/// Something that happened during the last [`Game::update`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// A bullet of `player` hit an asteroid, `score` is the player's score after the hit.
+    AsteroidDestroyed { player: usize, score: u32 },
+    /// The ship of `player` crashed but the game goes on, `lives` is what the
+    /// player has left. At 0 the player is out until the next game or round.
+    /// `killer` is the player that shot the ship, `None` for an asteroid.
+    LifeLost {
+        player: usize,
+        lives: u32,
+        killer: Option<usize>,
+    },
+    /// The last ship crashed and the game was reset, `score` is the score before the reset.
+    ShipCrashed { score: u32, high_score: u32 },
+    /// A versus round ended with one ship left, `winner` is `None` when the
+    /// last ships crashed at the same time.
+    RoundOver { round: u32, winner: Option<usize> },
+    /// `player` won the versus match and a new match started.
+    MatchWon { player: usize },
+    /// `player` flew into a power-up, see [`Game::power_ups`].
+    PowerUpCollected { player: usize, kind: PowerUpKind },
+}
+
+/// Something asteroids can collide with, as stored in [`Game::grid`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Collider {
+    Bullet(Handle),
+    /// Index of the player flying the ship.
+    Ship(usize),
+}
+
+/// An entry of the versus kill feed.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Kill {
+    pub time: f64,
+    /// `None` if the ship hit an asteroid.
+    pub killer: Option<usize>,
+    pub victim: usize,
+}
+
+/// Settings and progress of a versus match, see [`Game::versus`].
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Versus {
+    /// Round wins needed to win the match.
+    pub rounds_to_win: u32,
+    /// The current round, starting at 1.
+    pub round: u32,
+    /// The last [`KILL_FEED_LENGTH`] kills, oldest first.
+    pub kill_feed: Vec<Kill>,
+}
+
+/// Pushes `asteroid` out of the shield it overlaps as described by `contact`
+/// and reflects its velocity off the shield, which moves at `shield_velocity`.
+fn bounce_off_shield(asteroid: &mut Asteroid, shield_velocity: &Vec2, contact: &Contact) {
+    // The normal points from the asteroid towards the ship
+    let normal = &contact.normal;
+    asteroid.transform.pos.x -= normal.x * contact.depth;
+    asteroid.transform.pos.y -= normal.y * contact.depth;
+    asteroid.transform.apply();
+    let relative = vec2(
+        asteroid.velocity.x - shield_velocity.x,
+        asteroid.velocity.y - shield_velocity.y,
+    );
+    let approach = relative.dot(normal);
+    if approach > 0. {
+        asteroid.velocity.x -= 2. * approach * normal.x;
+        asteroid.velocity.y -= 2. * approach * normal.y;
+    }
+}
+
+/// Splits the shot `asteroid` into `pieces` and splits the new pieces again
+/// for every point of `damage` above 1. `spare` holds the pieces in between.
+fn shatter(
+    rules: &mut dyn Rules,
+    rng: &mut Pcg32,
+    asteroid: &Asteroid,
+    damage: u32,
+    pieces: &mut Vec<Asteroid>,
+    spare: &mut Vec<Asteroid>,
+) {
+    let first = pieces.len();
+    rules.split_asteroid(rng, asteroid, pieces);
+    for _ in 1..damage {
+        spare.clear();
+        spare.extend(pieces.drain(first..));
+        for piece in spare
+            .iter()
+            .filter(|piece| piece.transform.scale > MIN_ASTEROID_SCALE)
+        {
+            rules.split_asteroid(rng, piece, pieces);
+        }
+    }
+}
+
+/// Number of asteroids spawned at the start of `wave`, a single one after a
+/// (re)start and [`WAVE_ASTEROIDS`] for every later wave.
+pub fn wave_size(wave: u32) -> u32 {
+    if wave == 0 {
+        1
+    } else {
+        WAVE_ASTEROIDS
+    }
+}
+
+/// The whole simulation state.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    pub seed: u64,
+    pub rng: Pcg32,
+    /// One for a normal game, more for local co-op, see [`Game::with_players`].
+    pub players: Vec<Player>,
+    pub asteroids: Pool<Asteroid>,
+    pub bullets: Pool<Bullet>,
+    /// Score of all players together.
+    pub score: u32,
+    pub high_score: u32,
+    /// Lives every player starts a game with.
+    pub lives: u32,
+    /// Whether bullets hit the ships of other players, always the case in versus.
+    pub friendly_fire: bool,
+    /// Set when asteroids bounce off each other instead of passing through.
+    #[serde(default)]
+    pub asteroid_physics: Option<AsteroidPhysics>,
+    /// Whether shot asteroids drop power-ups.
+    #[serde(default)]
+    pub power_ups: bool,
+    /// How bullets fly, the original rules unless changed.
+    #[serde(default)]
+    pub ballistics: Ballistics,
+    /// Dropped power-ups waiting to be collected.
+    #[serde(default)]
+    pub pickups: Pool<PowerUp>,
+    /// Laser shots still on screen.
+    #[serde(default)]
+    pub beams: Pool<Beam>,
+    /// Set for a versus match, where the ships fight each other.
+    pub versus: Option<Versus>,
+    /// Simulated seconds since the game was created, scaled by `time_scale`.
+    pub time: f64,
+    /// Incremented every time the playfield is cleared, 0 is the single asteroid after a (re)start.
+    pub wave: u32,
+    pub god_mode: bool,
+    /// Multiplier applied to the delta time of every update.
+    pub time_scale: f32,
+    /// Events of the last update.
+    #[serde(skip)]
+    pub events: Vec<GameEvent>,
+    /// Bullets and ships sorted into cells, rebuilt every update before the
+    /// asteroids are checked against them.
+    #[serde(skip)]
+    pub grid: Grid<Collider>,
+    /// Buffers reused by every update so it doesn't allocate.
+    #[serde(skip)]
+    scratch: Scratch,
+}
+
+#[derive(Clone)]
+struct Scratch {
+    /// Pieces of the asteroids shot in this update.
+    pieces: Vec<Asteroid>,
+    /// Pieces that are split again, see [`Weapon::damage`](weapon::Weapon::damage).
+    shards: Vec<Asteroid>,
+    lasers: Vec<Laser>,
+    /// Index of the asteroid each laser hit with the player that fired it.
+    laser_hits: Vec<(usize, usize)>,
+    /// Per player.
+    crashed: Vec<bool>,
+    killers: Vec<Option<usize>>,
+    lives_lost: Vec<GameEvent>,
+    /// Asteroids by index, only used with [`Game::asteroid_physics`].
+    asteroid_grid: Grid<usize>,
+}
+
+impl Default for Scratch {
+    fn default() -> Self {
+        Self {
+            pieces: Vec::with_capacity(PIECE_CAPACITY),
+            shards: Vec::with_capacity(PIECE_CAPACITY),
+            lasers: vec![],
+            laser_hits: vec![],
+            crashed: vec![],
+            killers: vec![],
+            lives_lost: vec![],
+            asteroid_grid: Grid::default(),
+        }
+    }
+}
+
+impl Game {
+    /// A single player game that restarts when the ship crashes.
+    pub fn new(seed: u64) -> Self {
+        Self::with_players(seed, 1, 1)
+    }
+
+    /// A game for `players` players that share the asteroids, each with
+    /// `lives` lives. It restarts once every player is out.
+    pub fn with_players(seed: u64, players: usize, lives: u32) -> Self {
+        assert!(
+            players > 0 && lives > 0,
+            "a game needs at least one player and one life"
+        );
+        let mut rng = Pcg32::seed_from_u64(seed);
+        let players: Vec<Player> = (0..players)
+            .map(|i| Player::new(spawn_position(i, players), lives))
+            .collect();
+        let mut asteroids = Pool::with_capacity(ASTEROID_CAPACITY);
+        asteroids.insert(Asteroid::random(&mut rng, &players));
+        Self {
+            seed,
+            rng,
+            players,
+            asteroids,
+            bullets: Pool::with_capacity(BULLET_CAPACITY),
+            score: 0,
+            high_score: 0,
+            lives,
+            friendly_fire: false,
+            asteroid_physics: None,
+            power_ups: false,
+            ballistics: Ballistics::default(),
+            pickups: Pool::with_capacity(POWER_UP_CAPACITY),
+            beams: Pool::with_capacity(BEAM_CAPACITY),
+            versus: None,
+            time: 0.,
+            wave: 0,
+            god_mode: false,
+            time_scale: 1.,
+            events: Vec::with_capacity(EVENT_CAPACITY),
+            grid: Grid::default(),
+            scratch: Scratch::default(),
+        }
+    }
+
+    /// A versus match between `players` ships with `lives` lives per round.
+    /// The last ship left wins the round and the first player to win
+    /// `rounds_to_win` rounds wins the match.
+    pub fn versus(seed: u64, players: usize, lives: u32, rounds_to_win: u32) -> Self {
+        assert!(players > 1, "a versus match needs at least two players");
+        let mut game = Self::with_players(seed, players, lives);
+        game.versus = Some(Versus {
+            rounds_to_win: rounds_to_win.max(1),
+            round: 1,
+            kill_feed: vec![],
+        });
+        game
+    }
+
+    /// Replaces the playfield with the asteroids of `wave`.
+    pub fn start_wave(&mut self, wave: u32) {
+        self.start_wave_with_rules(wave, &mut DefaultRules);
+    }
+
+    pub fn start_wave_with_rules(&mut self, wave: u32, rules: &mut dyn Rules) {
+        self.wave = wave;
+        let asteroids = rules.wave_asteroids(self, wave);
+        self.asteroids.clear();
+        self.asteroids.extend(asteroids);
+    }
+
+    /// Resets everything except the high score after the last ship crashed
+    /// or a versus match was won.
+    fn reset(&mut self, rules: &mut dyn Rules) {
+        for player in &mut self.players {
+            player.score = 0;
+            player.kills = 0;
+            player.round_wins = 0;
+        }
+        self.score = 0;
+        if let Some(versus) = &mut self.versus {
+            versus.round = 1;
+            versus.kill_feed.clear();
+        }
+        self.start_round(rules);
+    }
+
+    /// Puts every ship back at its starting point with full lives and starts
+    /// over at wave 0, scores are kept.
+    fn start_round(&mut self, rules: &mut dyn Rules) {
+        self.bullets.clear();
+        self.pickups.clear();
+        self.beams.clear();
+        let count = self.players.len();
+        for (i, player) in self.players.iter_mut().enumerate() {
+            player.bullet_cooldown = DEFAULT_BULLET_COOLDOWN;
+            player.last_bullet = self.time - player.bullet_cooldown as f64 / 1000.;
+            player.respawn(spawn_position(i, count));
+            player.lives = self.lives;
+            player.invulnerable_until = 0.;
+            player.effects = Effects::default();
+        }
+        self.start_wave_with_rules(0, rules);
+    }
+
+    /// Ends the versus round after all ships but one are out.
+    fn end_round(&mut self, rules: &mut dyn Rules) {
+        let Some(versus) = &mut self.versus else {
+            return;
+        };
+        let winner = self.players.iter().position(Player::is_active);
+        if let Some(winner) = winner {
+            self.players[winner].round_wins += 1;
+        }
+        self.events.push(GameEvent::RoundOver {
+            round: versus.round,
+            winner,
+        });
+        versus.round += 1;
+
+        let rounds_to_win = versus.rounds_to_win;
+        match winner.filter(|w| self.players[*w].round_wins >= rounds_to_win) {
+            Some(player) => {
+                self.events.push(GameEvent::MatchWon { player });
+                self.reset(rules);
+            }
+            None => self.start_round(rules),
+        }
+    }
+
+    /// Moves the ship of `player`.
+    fn update_player(player: &mut Player, input: &Input, delta_time: f32) {
+        let ship = &mut player.ship;
+        if input.left {
+            ship.transform.rot -= 3.5 * delta_time;
+        }
+        if input.right {
+            ship.transform.rot += 3.5 * delta_time;
+        }
+
+        let (s, c) = (ship.transform.rot + FRAC_PI_2).sin_cos();
+
+        let mut moving = false;
+        if input.thrust {
+            moving = true;
+            ship.velocity.x += c * ship.acc * delta_time;
+            ship.velocity.y += s * ship.acc * delta_time;
+        }
+        if input.reverse {
+            moving = true;
+            ship.velocity.x -= c * ship.acc * delta_time;
+            ship.velocity.y -= s * ship.acc * delta_time;
+        }
+
+        let min_vel = 0.75;
+        let acc_mul = 1. / 1.2;
+
+        if !moving {
+            if ship.velocity.x >= min_vel {
+                ship.velocity.x -= ship.acc * acc_mul * delta_time;
+            } else if ship.velocity.x <= -min_vel {
+                ship.velocity.x += ship.acc * acc_mul * delta_time;
+            } else {
+                ship.velocity.x = 0.
+            }
+
+            if ship.velocity.y >= min_vel {
+                ship.velocity.y -= ship.acc * acc_mul * delta_time;
+            } else if ship.velocity.y <= -min_vel {
+                ship.velocity.y += ship.acc * acc_mul * delta_time;
+            } else {
+                ship.velocity.y = 0.
+            }
+        }
+
+        ship.update(delta_time);
+        player.shield.update(input.shield, delta_time);
+        let ship = &mut player.ship;
+        let horizontal_edge = (CANVAS_WIDTH - 1) as f32;
+        if ship.transform.pos.x < 0. {
+            ship.transform.pos.x = horizontal_edge;
+        } else if ship.transform.pos.x > horizontal_edge {
+            ship.transform.pos.x = 0.
+        }
+
+        let vertical_edge = (CANVAS_HEIGHT - 1) as f32;
+        if ship.transform.pos.y < 0. {
+            ship.transform.pos.y = vertical_edge;
+        } else if ship.transform.pos.y > vertical_edge {
+            ship.transform.pos.y = 0.
+        }
+    }
+
+    /// Switches and fires the weapon of `player` as far as the cooldown and
+    /// [`Ballistics::max_bullets`] allow. Lasers are added to `lasers` and
+    /// hit when the update resolves them.
+    fn fire(
+        player: &mut Player,
+        index: usize,
+        input: &Input,
+        time: f64,
+        ballistics: &Ballistics,
+        bullets: &mut Pool<Bullet>,
+        lasers: &mut Vec<Laser>,
+    ) {
+        if input.switch_weapon {
+            player.weapon = player.weapon.next();
+        }
+        let ship = &player.ship;
+        let effects = &player.effects;
+        let weapon = player.weapon.stats();
+        let mut cooldown = player.bullet_cooldown as f64 * weapon.cooldown;
+        if effects.active(PowerUpKind::RapidFire, time) {
+            cooldown /= 2.;
+        }
+        if input.fire && (time - player.last_bullet) * 1000. >= cooldown {
+            // Lasers leave no bullets behind, so only they fire with every bullet out
+            let in_flight = || bullets.iter().filter(|b| b.owner == index).count();
+            let mut room = ballistics
+                .max_bullets
+                .map(|max| (max as usize).saturating_sub(in_flight()));
+            if room == Some(0) && player.weapon != WeaponKind::Laser {
+                return;
+            }
+            // Mines are dropped behind the ship, everything else leaves its nose
+            let offset = if player.weapon == WeaponKind::Mine {
+                -ship.transform.scale
+            } else {
+                ship.transform.scale
+            };
+            for angle in weapon.angles(effects.active(PowerUpKind::SpreadShot, time)) {
+                let dir = (ship.transform.rot + FRAC_PI_2 + angle).sin_cos();
+                let dir = vec2(dir.0, dir.1);
+                let pos = vec2(
+                    ship.transform.pos.x + dir.y * offset,
+                    ship.transform.pos.y + dir.x * offset,
+                );
+                if player.weapon == WeaponKind::Laser {
+                    lasers.push(Laser {
+                        origin: pos,
+                        dir: vec2(dir.y, dir.x),
+                        owner: index,
+                    });
+                    continue;
+                }
+                if room == Some(0) {
+                    break;
+                }
+                let mut bullet = Bullet::new(pos, dir, index);
+                bullet.weapon = player.weapon;
+                // Mines stay where they were dropped
+                if ballistics.inherit_velocity && player.weapon != WeaponKind::Mine {
+                    bullet.inherited = ship.velocity.clone();
+                }
+                if effects.active(PowerUpKind::Piercing, time) {
+                    bullet.pierce = powerup::PIERCE_HITS;
+                }
+                bullets.insert(bullet);
+                room = room.map(|room| room - 1);
+            }
+            player.last_bullet = time;
+        }
+    }
+
+    /// Updates the game with the input of the first player.
+    pub fn update(&mut self, input: &Input, delta_time: f32) {
+        self.update_with_rules(input, delta_time, &mut DefaultRules);
+    }
+
+    /// Like [`Game::update`] but with `rules` in place of the standard rules.
+    pub fn update_with_rules(&mut self, input: &Input, delta_time: f32, rules: &mut dyn Rules) {
+        self.update_players(std::slice::from_ref(input), delta_time, rules);
+    }
+
+    /// Updates the game with one input per player in the order of `players`,
+    /// players without an input don't press anything.
+    pub fn update_players(&mut self, inputs: &[Input], delta_time: f32, rules: &mut dyn Rules) {
+        let delta_time = delta_time * self.time_scale;
+        self.time += delta_time as f64;
+        self.events.clear();
+        for (i, player) in self.players.iter_mut().enumerate() {
+            if !player.is_active() {
+                continue;
+            }
+            let input = inputs.get(i).copied().unwrap_or_default();
+            Self::update_player(player, &input, delta_time);
+            Self::fire(
+                player,
+                i,
+                &input,
+                self.time,
+                &self.ballistics,
+                &mut self.bullets,
+                &mut self.scratch.lasers,
+            );
+        }
+
+        let (asteroids, range) = (&self.asteroids, self.ballistics.range);
+        self.bullets.retain_mut(|b| {
+            if b.weapon == WeaponKind::Missile {
+                b.steer(asteroids, delta_time);
+            }
+            let velocity = b.velocity();
+            b.pos.x += velocity.x * delta_time;
+            b.pos.y += velocity.y * delta_time;
+            // The range counts the bullet's own speed, so a bullet fired backwards
+            // at the ship's speed still runs out
+            b.traveled += b.weapon.stats().speed * delta_time;
+            b.age += delta_time;
+            if b.pos.x < 0. {
+                b.pos.x = CANVAS_WIDTH as f32;
+                b.wrap_count += 1;
+            } else if b.pos.x > CANVAS_WIDTH as f32 {
+                b.pos.x = 0.;
+                b.wrap_count += 1;
+            }
+            if b.pos.y < 0. {
+                b.pos.y = CANVAS_HEIGHT as f32;
+                b.wrap_count += 1;
+            } else if b.pos.y > CANVAS_HEIGHT as f32 {
+                b.pos.y = 0.;
+                b.wrap_count += 1;
+            }
+
+            b.alive(range)
+        });
+
+        let time = self.time;
+        self.pickups.retain_mut(|pickup| {
+            pickup.pos.x =
+                (pickup.pos.x + pickup.velocity.x * delta_time).rem_euclid(CANVAS_WIDTH as f32);
+            pickup.pos.y =
+                (pickup.pos.y + pickup.velocity.y * delta_time).rem_euclid(CANVAS_HEIGHT as f32);
+            time < pickup.expires
+        });
+        self.beams.retain(|beam| time < beam.expires);
+
+        if self.asteroids.is_empty() {
+            self.start_wave_with_rules(self.wave + 1, rules);
+        }
+
+        if let Some(physics) = &self.asteroid_physics {
+            physics::collide_asteroids(
+                &mut self.asteroids,
+                &mut self.scratch.asteroid_grid,
+                physics,
+            );
+        }
+
+        let Self {
+            rng,
+            players,
+            asteroids,
+            bullets,
+            pickups,
+            beams,
+            score,
+            friendly_fire,
+            power_ups,
+            versus,
+            time,
+            events,
+            grid,
+            scratch,
+            ..
+        } = self;
+
+        let crashed = &mut scratch.crashed;
+        crashed.clear();
+        crashed.resize(players.len(), false);
+        let killers = &mut scratch.killers;
+        killers.clear();
+        killers.resize(players.len(), None);
+
+        // Lasers hit the nearest asteroid or ship along their ray, passing
+        // through asteroids an earlier laser already destroys
+        let laser_hits = &mut scratch.laser_hits;
+        laser_hits.clear();
+        for laser in scratch.lasers.drain(..) {
+            let mut length = WeaponKind::Laser.stats().range();
+            let mut target = None;
+            for (i, asteroid) in asteroids.iter().enumerate() {
+                if laser_hits.iter().any(|(hit, _)| *hit == i) {
+                    continue;
+                }
+                let points = asteroid.transform.transformed();
+                if let Some(distance) =
+                    math::ray_polygon_distance(&laser.origin, &laser.dir, points)
+                {
+                    if distance < length {
+                        length = distance;
+                        target = Some(i);
+                    }
+                }
+            }
+            let mut victim = None;
+            if *friendly_fire || versus.is_some() {
+                for (i, p) in players.iter().enumerate() {
+                    if i == laser.owner || !p.is_active() || *time < p.invulnerable_until {
+                        continue;
+                    }
+                    let (left, right, top, bottom) = p.ship.hitbox_bounds();
+                    let corners = [
+                        vec2(left, top),
+                        vec2(right, top),
+                        vec2(right, bottom),
+                        vec2(left, bottom),
+                    ];
+                    if let Some(distance) =
+                        math::ray_polygon_distance(&laser.origin, &laser.dir, &corners)
+                    {
+                        if distance < length {
+                            length = distance;
+                            victim = Some(i);
+                        }
+                    }
+                }
+            }
+            if let Some(i) = victim {
+                crashed[i] = true;
+                killers[i] = Some(laser.owner);
+            } else if let Some(i) = target {
+                laser_hits.push((i, laser.owner));
+            }
+            beams.insert(Beam {
+                to: vec2(
+                    laser.origin.x + laser.dir.x * length,
+                    laser.origin.y + laser.dir.y * length,
+                ),
+                from: laser.origin,
+                owner: laser.owner,
+                expires: *time + weapon::BEAM_DURATION,
+            });
+        }
+
+        grid.clear();
+        for (handle, b) in bullets.iter_with_handles() {
+            grid.insert_point(b.pos.x, b.pos.y, Collider::Bullet(handle));
+        }
+        for (i, player) in players.iter().enumerate() {
+            if player.is_active() {
+                let bounds = if player.shielded(*time) {
+                    player.ship.shield_bounds()
+                } else {
+                    player.ship.bounds()
+                };
+                grid.insert(bounds, Collider::Ship(i));
+            }
+        }
+
+        let pieces = &mut scratch.pieces;
+        pieces.clear();
+        let slowed = players
+            .iter()
+            .any(|p| p.effects.active(PowerUpKind::TimeSlow, *time));
+        let asteroid_delta_time = if slowed {
+            delta_time * powerup::TIME_SLOW_FACTOR
+        } else {
+            delta_time
+        };
+        let shards = &mut scratch.shards;
+        let mut index = 0;
+        asteroids.retain_mut(|asteroid| {
+            let current = index;
+            index += 1;
+            asteroid.transform.pos.x += asteroid.velocity.x * asteroid_delta_time;
+            asteroid.transform.pos.y += asteroid.velocity.y * asteroid_delta_time;
+            asteroid.transform.rot += asteroid.spin * asteroid_delta_time;
+            asteroid.transform.apply();
+
+            // The first bullet inside the asteroid in the current order of `bullets` hits it
+            let mut hit: Option<(usize, Handle)> = None;
+            for collider in grid.query(asteroid.bounds()) {
+                match *collider {
+                    Collider::Ship(i) => {
+                        let ship = &players[i].ship;
+                        if players[i].shielded(*time) {
+                            let points = asteroid.transform.transformed();
+                            if let Some(contact) = math::circle_polygon_contact(
+                                &ship.transform.pos,
+                                ship.shield_radius(),
+                                points,
+                            ) {
+                                bounce_off_shield(asteroid, &ship.velocity, &contact);
+                            }
+                            continue;
+                        }
+                        crashed[i] |= ship
+                            .hitbox
+                            .iter()
+                            .any(|Vec2 { x, y }| asteroid.contains(*x, *y));
+                        crashed[i] |= asteroid.contains(ship.transform.pos.x, ship.transform.pos.y);
+                    }
+                    Collider::Bullet(handle) => {
+                        // Already removed by another asteroid
+                        let Some(index) = bullets.index_of(handle) else {
+                            continue;
+                        };
+                        let b = &bullets[index];
+                        if hit.is_none_or(|(first, _)| index < first)
+                            && b.can_hit(*time)
+                            && asteroid.contains(b.pos.x, b.pos.y)
+                        {
+                            hit = Some((index, handle));
+                        }
+                    }
+                }
+            }
+
+            // A laser hits the moment it fires, so it beats any bullet and
+            // the bullet flies on
+            let laser = laser_hits
+                .iter()
+                .find(|(i, _)| *i == current)
+                .map(|&(_, owner)| (owner, WeaponKind::Laser.stats().damage));
+            let shot = laser.or_else(|| {
+                let (_, handle) = hit?;
+                let bullet = bullets.get_mut(handle).expect("the bullet was just found");
+                let shot = (bullet.owner, bullet.weapon.stats().damage);
+                if bullet.pierce > 0 {
+                    bullet.pierce -= 1;
+                    bullet.last_hit = Some(*time);
+                } else {
+                    bullets.remove(handle);
+                }
+                Some(shot)
+            });
+            let destroyed = shot.is_some();
+            if let Some((owner, damage)) = shot {
+                let points = rules.asteroid_score(asteroid);
+                *score += points;
+                if let Some(player) = players.get_mut(owner) {
+                    let previous_score = player.score;
+                    player.score += points;
+                    player.ship.acc += (player.score as f32) / 32.;
+                    // Every 5 points shorten the cooldown
+                    for _ in previous_score / 5..player.score / 5 {
+                        player.bullet_cooldown =
+                            player.bullet_cooldown.saturating_sub(200).max(700);
+                    }
+                    events.push(GameEvent::AsteroidDestroyed {
+                        player: owner,
+                        score: player.score,
+                    });
+                }
+
+                if *power_ups && rng.gen::<f32>() < powerup::drop_chance(asteroid.transform.scale) {
+                    let kind = PowerUpKind::random(rng);
+                    pickups.insert(PowerUp::new(
+                        kind,
+                        asteroid.transform.pos.clone(),
+                        asteroid.velocity.clone(),
+                        *time,
+                    ));
+                }
+                shatter(rules, rng, asteroid, damage, pieces, shards);
+            }
+
+            if asteroid.transform.pos.x < 0. {
+                asteroid.transform.pos.x = CANVAS_WIDTH as f32
+            } else if asteroid.transform.pos.x > CANVAS_WIDTH as f32 {
+                asteroid.transform.pos.x = 0.
+            }
+            if asteroid.transform.pos.y < 0. {
+                asteroid.transform.pos.y = CANVAS_HEIGHT as f32
+            } else if asteroid.transform.pos.y > CANVAS_HEIGHT as f32 {
+                asteroid.transform.pos.y = 0.
+            }
+
+            !destroyed && asteroid.transform.scale > MIN_ASTEROID_SCALE
+        });
+
+        asteroids.extend(pieces.drain(..));
+
+        if *friendly_fire || versus.is_some() {
+            bullets.retain(|b| {
+                let hit = players.iter().enumerate().position(|(i, p)| {
+                    i != b.owner
+                        && p.is_active()
+                        && *time >= p.invulnerable_until
+                        && p.ship.contains(b.pos.x, b.pos.y)
+                });
+                if let Some(i) = hit {
+                    crashed[i] = true;
+                    killers[i] = Some(b.owner);
+                }
+                hit.is_none()
+            });
+        }
+
+        for (i, player) in self.players.iter_mut().enumerate() {
+            if !player.is_active() {
+                continue;
+            }
+            let pos = player.ship.transform.pos.clone();
+            let (time, events) = (self.time, &mut self.events);
+            self.pickups.retain(|pickup| {
+                let offset = vec2(pickup.pos.x - pos.x, pickup.pos.y - pos.y);
+                if offset.length() > powerup::PICKUP_RADIUS {
+                    return true;
+                }
+                player.collect(pickup.kind, time);
+                events.push(GameEvent::PowerUpCollected {
+                    player: i,
+                    kind: pickup.kind,
+                });
+                false
+            });
+        }
+
+        if !self.god_mode {
+            let Scratch {
+                crashed,
+                killers,
+                lives_lost,
+                ..
+            } = &mut self.scratch;
+            let count = self.players.len();
+            lives_lost.clear();
+            for (i, player) in self.players.iter_mut().enumerate() {
+                if !crashed[i]
+                    || self.time < player.invulnerable_until
+                    || player.shielded(self.time)
+                {
+                    continue;
+                }
+                player.lives -= 1;
+                if player.is_active() {
+                    player.respawn(spawn_position(i, count));
+                    player.invulnerable_until = self.time + RESPAWN_INVULNERABILITY;
+                }
+                lives_lost.push(GameEvent::LifeLost {
+                    player: i,
+                    lives: player.lives,
+                    killer: killers[i],
+                });
+            }
+
+            for event in lives_lost.iter() {
+                let GameEvent::LifeLost { player, killer, .. } = *event else {
+                    continue;
+                };
+                if let Some(killer) = killer {
+                    self.players[killer].kills += 1;
+                }
+                if let Some(versus) = &mut self.versus {
+                    if versus.kill_feed.len() == KILL_FEED_LENGTH {
+                        versus.kill_feed.remove(0);
+                    }
+                    versus.kill_feed.push(Kill {
+                        time: self.time,
+                        killer,
+                        victim: player,
+                    });
+                }
+            }
+
+            let active = self.players.iter().filter(|p| p.is_active()).count();
+            if self.versus.is_some() && active <= 1 && !lives_lost.is_empty() {
+                self.events.append(lives_lost);
+                self.end_round(rules);
+            } else if active > 0 {
+                self.events.append(lives_lost);
+            } else {
+                self.high_score = self.high_score.max(self.score);
+                self.events.push(GameEvent::ShipCrashed {
+                    score: self.score,
+                    high_score: self.high_score,
+                });
+                self.reset(rules);
+            }
+        }
+
+        for player in &mut self.players {
+            player.ship.transform.apply();
+        }
+        rules.after_update(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bullet_fired_backwards_at_ship_speed_runs_out() {
+        let mut game = Game::new(1);
+        game.god_mode = true;
+        game.ballistics = Ballistics::ARCADE;
+        // Park the asteroid in a corner so the bullet can't hit it
+        for asteroid in game.asteroids.iter_mut() {
+            asteroid.transform.pos = vec2(20., 20.);
+            asteroid.velocity = vec2(0., 0.);
+            asteroid.spin = 0.;
+        }
+        // A new ship fires towards +y, flying towards -y as fast as its bullets cancels them out
+        game.players[0].ship.velocity = vec2(0., -BULLET_SPEED);
+        let fire = Input {
+            fire: true,
+            ..Input::default()
+        };
+        game.update(&fire, FIXED_DELTA_TIME);
+        assert_eq!(game.bullets.len(), 1);
+        let bullet = game.bullets.iter().next().unwrap();
+        // Short of the deceleration of the ship during the tick it fired
+        assert!(bullet.velocity().length() < 5.);
+
+        let lifetime = weapon::ARCADE_BULLET_RANGE / BULLET_SPEED;
+        let ticks = (lifetime / FIXED_DELTA_TIME) as u32;
+        for _ in 0..ticks - 2 {
+            game.update(&Input::default(), FIXED_DELTA_TIME);
+        }
+        assert_eq!(game.bullets.len(), 1);
+        for _ in 0..3 {
+            game.update(&Input::default(), FIXED_DELTA_TIME);
+        }
+        assert!(game.bullets.is_empty());
+    }
+
+    #[test]
+    fn laser_beats_a_bullet_in_the_same_asteroid() {
+        let mut game = Game::new(1);
+        game.players[0].weapon = WeaponKind::Laser;
+        // The laser's cooldown is longer than the time before the first shot
+        game.players[0].last_bullet = -10.;
+        // A new ship faces +y, put the asteroid in front of it with a bullet inside
+        let ship = game.players[0].ship.transform.pos.clone();
+        let target = vec2(ship.x, ship.y + 40.);
+        for asteroid in game.asteroids.iter_mut() {
+            asteroid.transform.pos = target.clone();
+            asteroid.velocity = vec2(0., 0.);
+            asteroid.spin = 0.;
+            asteroid.transform.apply();
+        }
+        game.bullets
+            .insert(Bullet::new(target.clone(), vec2(1., 0.), 0));
+        let fire = Input {
+            fire: true,
+            ..Input::default()
+        };
+        game.update(&fire, FIXED_DELTA_TIME);
+
+        assert_eq!(game.players[0].score, 1);
+        assert_eq!(game.bullets.len(), 1);
+        let beam = game.beams.iter().next().expect("the laser left a beam");
+        assert!(beam.to.y < target.y);
+    }
+}
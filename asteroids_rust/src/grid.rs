@@ -0,0 +1,107 @@
+//! Uniform grid over the playfield for finding collision candidates without
+//! testing every pair, see [`Game::grid`](crate::game::Game::grid).
+
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+
+/// Side length of a cell, about the size of the largest asteroid.
+pub const GRID_CELL_SIZE: f32 = 32.;
+/// Items a cell holds before it allocates.
+const CELL_CAPACITY: usize = 4;
+
+/// Axis aligned box as (left, right, top, bottom), like [`crate::game::Asteroid::bounds`].
+pub type Bounds = (f32, f32, f32, f32);
+
+/// Items sorted into square cells by their bounding boxes. Coordinates
+/// outside the grid wrap around like the playfield, so a box that sticks out
+/// of one edge lands in the cells along the opposite edge as well.
+#[derive(Clone)]
+pub struct Grid<T> {
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<T>>,
+}
+
+impl<T> Default for Grid<T> {
+    /// A grid covering the canvas with [`GRID_CELL_SIZE`] cells.
+    fn default() -> Self {
+        Self::new(GRID_CELL_SIZE, CANVAS_WIDTH as f32, CANVAS_HEIGHT as f32)
+    }
+}
+
+/// Indices of the `count` wrapping cells that `min..=max` overlaps.
+fn span(min: f32, max: f32, cell_size: f32, count: usize) -> impl Iterator<Item = usize> + Clone {
+    let first = (min / cell_size).floor() as i64;
+    let last = ((max / cell_size).floor() as i64).max(first);
+    let len = ((last - first + 1) as usize).min(count);
+    (0..len).map(move |i| (first + i as i64).rem_euclid(count as i64) as usize)
+}
+
+impl<T> Grid<T> {
+    pub fn new(cell_size: f32, width: f32, height: f32) -> Self {
+        let columns = (width / cell_size).ceil().max(1.) as usize;
+        let rows = (height / cell_size).ceil().max(1.) as usize;
+        Self {
+            cell_size,
+            columns,
+            rows,
+            cells: (0..columns * rows)
+                .map(|_| Vec::with_capacity(CELL_CAPACITY))
+                .collect(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Number of cells horizontally and vertically.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// Empties every cell, keeping their memory for the next rebuild.
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    /// Indices (`row * columns + column`) of the cells that `bounds` overlaps.
+    pub fn cells(&self, bounds: Bounds) -> impl Iterator<Item = usize> + Clone {
+        let (left, right, top, bottom) = bounds;
+        let columns = self.columns;
+        let xs = span(left, right, self.cell_size, columns);
+        span(top, bottom, self.cell_size, self.rows)
+            .flat_map(move |y| xs.clone().map(move |x| y * columns + x))
+    }
+
+    /// Items in the cell with index `cell`, see [`Grid::cells`].
+    pub fn cell(&self, cell: usize) -> &[T] {
+        &self.cells[cell]
+    }
+
+    /// Candidates for everything inside `bounds`. Items whose boxes span
+    /// several of those cells are returned once per cell.
+    pub fn query(&self, bounds: Bounds) -> impl Iterator<Item = &T> {
+        self.cells(bounds).flat_map(|cell| self.cells[cell].iter())
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    /// Adds `item` to every cell `bounds` overlaps.
+    pub fn insert(&mut self, bounds: Bounds, item: T) {
+        let (left, right, top, bottom) = bounds;
+        let columns = self.columns;
+        for y in span(top, bottom, self.cell_size, self.rows) {
+            for x in span(left, right, self.cell_size, columns) {
+                self.cells[y * columns + x].push(item.clone());
+            }
+        }
+    }
+
+    /// Adds `item` to the cell containing the point.
+    pub fn insert_point(&mut self, x: f32, y: f32, item: T) {
+        self.insert((x, x, y, y), item);
+    }
+}
@@ -0,0 +1,156 @@
+//! Runs the game without a window, rendering frames into memory and
+//! exporting them as PNG files.
+
+use crate::debug::DebugOverlay;
+use crate::game::{Game, FIXED_DELTA_TIME};
+use crate::record::{RecordError, Recorder};
+use crate::render;
+use crate::replay::ReplayPlayer;
+use crate::rules::Rules;
+use crate::trace::TraceWriter;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+pub struct HeadlessOptions {
+    /// Number of frames to simulate, frame 0 is the initial state.
+    /// 0 runs until the end of the replay when there is one.
+    pub frames: u64,
+    /// Export every Nth frame.
+    pub png_every: Option<u64>,
+    /// Export these frames in addition to `png_every`.
+    pub png_frames: Vec<u64>,
+    pub out_dir: PathBuf,
+    /// Integer upscaling factor applied to the canvas resolution.
+    pub scale: usize,
+    /// Write a conformance trace of the run to this file, see [`crate::trace`].
+    pub trace: Option<PathBuf>,
+}
+
+impl Default for HeadlessOptions {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            png_every: None,
+            png_frames: vec![],
+            out_dir: PathBuf::from("frames"),
+            scale: 1,
+            trace: None,
+        }
+    }
+}
+
+impl HeadlessOptions {
+    fn exports(&self, frame: u64) -> bool {
+        self.png_every.is_some_and(|n| frame.is_multiple_of(n)) || self.png_frames.contains(&frame)
+    }
+}
+
+/// A frame buffer that the game can be rendered into without a window.
+pub struct FrameBuffer {
+    pub pixels: Vec<u32>,
+    pub size: (usize, usize),
+}
+
+impl FrameBuffer {
+    /// A buffer at `scale` times the canvas resolution.
+    pub fn new(scale: usize) -> Self {
+        let size = (CANVAS_WIDTH * scale, CANVAS_HEIGHT * scale);
+        Self {
+            pixels: vec![0; size.0 * size.1],
+            size,
+        }
+    }
+
+    pub fn render(&mut self, game: &Game, debug_overlay: &DebugOverlay) {
+        render::render(&mut self.pixels, self.size, game, debug_overlay);
+    }
+
+    /// The frame as tightly packed 8 bit RGB triplets.
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|p| [(p >> 16) as u8, (p >> 8) as u8, *p as u8])
+            .collect()
+    }
+
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.size.0 as u32, self.size.1 as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgb())
+    }
+}
+
+/// Simulates `options.frames` frames of `game`, writing the selected frames to
+/// `options.out_dir` as `frame_<number>.png`. Inputs come from `replay` if
+/// there is one and are empty otherwise, every frame is passed to `recorder`
+/// and every update is traced if `options.trace` is set. The game follows `rules`.
+pub fn run(
+    mut game: Game,
+    options: &HeadlessOptions,
+    mut replay: Option<ReplayPlayer>,
+    mut recorder: Option<Recorder>,
+    rules: &mut dyn Rules,
+) -> Result<(), RecordError> {
+    if options.png_every.is_some() || !options.png_frames.is_empty() {
+        fs::create_dir_all(&options.out_dir)?;
+    }
+    let mut frame_buffer = FrameBuffer::new(options.scale);
+    let debug_overlay = DebugOverlay::default();
+    let mut trace = match &options.trace {
+        Some(path) => Some(TraceWriter::create(path, &game)?),
+        None => None,
+    };
+
+    let last_frame = match (options.frames, &replay) {
+        (0, Some(_)) => u64::MAX,
+        (frames, _) => frames,
+    };
+    let mut delta_time = 0.;
+    for frame in 0..last_frame {
+        if frame > 0 {
+            let inputs;
+            (inputs, delta_time) = match &mut replay {
+                Some(player) => match player.next_tick() {
+                    Some(tick) => tick,
+                    None => {
+                        println!("Replay finished after {frame} frames");
+                        break;
+                    }
+                },
+                None => (vec![], FIXED_DELTA_TIME),
+            };
+            game.update_players(&inputs, delta_time, rules);
+            if let Some(trace) = &mut trace {
+                let input = inputs.first().copied().unwrap_or_default();
+                trace.record(&game, &input, delta_time)?;
+            }
+        }
+
+        if let Some(recorder) = &mut recorder {
+            recorder.capture(&game, &debug_overlay, delta_time)?;
+        }
+
+        if options.exports(frame) {
+            frame_buffer.render(&game, &debug_overlay);
+            let path = options.out_dir.join(format!("frame_{frame:06}.png"));
+            frame_buffer.write_png(&path)?;
+            println!("Wrote {}", path.display());
+        }
+    }
+
+    if let Some(trace) = trace {
+        println!("Traced {} updates", trace.finish()?);
+    }
+    if let Some(recorder) = recorder {
+        println!("Recorded {} frames", recorder.finish()?);
+    }
+
+    Ok(())
+}
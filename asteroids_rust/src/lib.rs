@@ -0,0 +1,38 @@
+//! The Asteroids simulation, renderer and tooling around them.
+//!
+//! The windowed game lives in `main.rs`. Everything here works without a
+//! window so the game can also be driven headless, e.g. through [`env`].
+
+pub mod bot;
+pub mod console;
+pub mod debug;
+pub mod env;
+pub mod font;
+pub mod game;
+pub mod grid;
+pub mod headless;
+pub mod math;
+pub mod net;
+pub mod physics;
+pub mod pool;
+pub mod powerup;
+pub mod record;
+pub mod render;
+pub mod replay;
+pub mod rules;
+pub mod save;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod shape;
+pub mod spectate;
+pub mod stress;
+pub mod trace;
+pub mod weapon;
+
+// Gameboy Resoultion * 2
+pub const CANVAS_WIDTH: usize = 320;
+pub const CANVAS_HEIGHT: usize = 288;
+
+// Gameboy Resolution
+// pub const CANVAS_WIDTH: usize = 160;
+// pub const CANVAS_HEIGHT: usize = 144;
@@ -1,8 +1,26 @@
-use framebrush::{Canvas, RGBu32, GREEN, RED, YELLOW};
-use math::{vec2, Transform, Vec2};
-use rand::{rngs::ThreadRng, Rng};
+#[cfg(feature = "scripting")]
+use asteroids_rust::script::ScriptRules;
+use asteroids_rust::{
+    bot::{self, Autopilot},
+    console::Console,
+    debug::DebugOverlay,
+    game::{Game, GameEvent, Input, DEFAULT_LIVES, DEFAULT_ROUNDS_TO_WIN, FIXED_DELTA_TIME},
+    headless,
+    net::{self, LossyTransport, MatchSettings, Session, UdpTransport},
+    record::Recorder,
+    render,
+    replay::{Replay, ReplayPlayer},
+    rules::{DefaultRules, Rules},
+    save,
+    shape::{self, ShapeSet},
+    spectate::Broadcaster,
+    stress,
+    weapon::Ballistics,
+    CANVAS_HEIGHT, CANVAS_WIDTH,
+};
+use framebrush::Canvas;
+use options::{NetMode, NetOptions, Options, USAGE};
 use std::{
-    f32::consts::{FRAC_PI_2, PI},
     num::NonZeroU32,
     time::{Duration, Instant},
 };
@@ -13,171 +31,286 @@ use winit::{
     window::WindowBuilder,
 };
 
-mod math;
+mod options;
 
 const SCREEN_WIDTH: u32 = 640;
 const SCREEN_HEIGHT: u32 = 576;
-
-// Gameboy Resoultion * 2
-const CANVAS_WIDTH: usize = 320;
-const CANVAS_HEIGHT: usize = 288;
-
-// Gameboy Resolution
-// const CANVAS_WIDTH: usize = 160;
-// const CANVAS_HEIGHT: usize = 144;
-
-const DANGER_ZONE: f32 = (1. / 20.) * (CANVAS_HEIGHT as f32);
-const DEFAULT_ACCELERATION: f32 = 25.;
-const DEFAULT_BULLET_COOLDOWN: u64 = 1100;
-const BULLET_COLOR: RGBu32 = RGBu32::Rgb(86, 182, 194);
-
-struct Ship {
-    transform: Transform<3>,
-    velocity: Vec2,
-    acc: f32,
-    hitbox: [Vec2; 4],
+/// Updates per asteroid count of `--stress`.
+const STRESS_UPDATES: u32 = 300;
+/// Seconds to wait for the other player of an online game.
+const CONNECT_TIMEOUT: u64 = 60;
+
+/// Keys that control one ship.
+struct KeyMap {
+    left: VirtualKeyCode,
+    right: VirtualKeyCode,
+    thrust: VirtualKeyCode,
+    reverse: VirtualKeyCode,
+    fire: VirtualKeyCode,
+    shield: VirtualKeyCode,
+    switch_weapon: VirtualKeyCode,
 }
 
-impl Ship {
-    fn update(&mut self, delta_time: f32) {
-        self.transform.pos.x += self.velocity.x * delta_time;
-        self.transform.pos.y += self.velocity.y * delta_time;
-
-        self.hitbox[2] = self.transform.vertices[0].clone() * (self.transform.scale / 2.);
+const SINGLE_PLAYER_KEYS: KeyMap = KeyMap {
+    left: VirtualKeyCode::Left,
+    right: VirtualKeyCode::Right,
+    thrust: VirtualKeyCode::Up,
+    reverse: VirtualKeyCode::Down,
+    fire: VirtualKeyCode::X,
+    shield: VirtualKeyCode::C,
+    switch_weapon: VirtualKeyCode::V,
+};
 
-        self.hitbox[3] = vec2(
-            self.transform.vertices[1].x * self.transform.scale / 2.,
-            self.hitbox[2].y,
-        );
+/// Keys of each player in a multiplayer game, the first two use one side of the keyboard each.
+const MULTIPLAYER_KEYS: [KeyMap; 4] = [
+    KeyMap {
+        left: VirtualKeyCode::A,
+        right: VirtualKeyCode::D,
+        thrust: VirtualKeyCode::W,
+        reverse: VirtualKeyCode::S,
+        fire: VirtualKeyCode::Space,
+        shield: VirtualKeyCode::E,
+        switch_weapon: VirtualKeyCode::Q,
+    },
+    KeyMap {
+        left: VirtualKeyCode::Left,
+        right: VirtualKeyCode::Right,
+        thrust: VirtualKeyCode::Up,
+        reverse: VirtualKeyCode::Down,
+        fire: VirtualKeyCode::Return,
+        shield: VirtualKeyCode::RShift,
+        switch_weapon: VirtualKeyCode::RControl,
+    },
+    KeyMap {
+        left: VirtualKeyCode::J,
+        right: VirtualKeyCode::L,
+        thrust: VirtualKeyCode::I,
+        reverse: VirtualKeyCode::K,
+        fire: VirtualKeyCode::H,
+        shield: VirtualKeyCode::U,
+        switch_weapon: VirtualKeyCode::Y,
+    },
+    KeyMap {
+        left: VirtualKeyCode::Numpad4,
+        right: VirtualKeyCode::Numpad6,
+        thrust: VirtualKeyCode::Numpad8,
+        reverse: VirtualKeyCode::Numpad5,
+        fire: VirtualKeyCode::Numpad0,
+        shield: VirtualKeyCode::Numpad7,
+        switch_weapon: VirtualKeyCode::Numpad9,
+    },
+];
+
+/// Connects to the other player of an online game, see `--host` and `--join`.
+fn connect(options: &Options, net: &NetOptions) -> Session<LossyTransport<UdpTransport>> {
+    let timeout = Duration::from_secs(CONNECT_TIMEOUT);
+    let session = match &net.mode {
+        NetMode::Host(port) => {
+            let transport = UdpTransport::host(*port)
+                .unwrap_or_else(|e| panic!("Couldn't listen on port {port}: {e}"));
+            let settings = MatchSettings {
+                seed: options.seed.unwrap_or_else(rand::random),
+                versus: options.versus.is_some(),
+                lives: options.lives.unwrap_or(DEFAULT_LIVES),
+                rounds_to_win: options.rounds.unwrap_or(DEFAULT_ROUNDS_TO_WIN),
+                asteroid_physics: options.asteroid_physics,
+                power_ups: options.power_ups,
+                ballistics: options.ballistics,
+            };
+            println!("Waiting for the other player on port {port}...");
+            let transport = LossyTransport::new(transport, net.conditions, rand::random());
+            Session::host(transport, settings, net.config, timeout)
+        }
+        NetMode::Join(address) => {
+            let transport = UdpTransport::join(address.as_str())
+                .unwrap_or_else(|e| panic!("Couldn't join '{address}': {e}"));
+            println!("Joining {address}...");
+            let transport = LossyTransport::new(transport, net.conditions, rand::random());
+            Session::join(transport, net.config, timeout)
+        }
+    };
+    let session = session.unwrap_or_else(|e| panic!("Couldn't connect: {e}"));
+    println!("Connected, you are Player {}", session.local_player() + 1);
+    session
+}
 
-        self.hitbox[0] = vec2(
-            self.hitbox[2].x,
-            self.transform.vertices[2].y * self.transform.scale / 2.,
+fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        std::process::exit(2);
+    });
+    if options.help {
+        println!("{USAGE}");
+        return;
+    }
+    if let Some(path) = &options.shapes {
+        let shapes =
+            ShapeSet::load(path).unwrap_or_else(|e| panic!("Couldn't load shapes '{path}': {e}"));
+        assert!(
+            shape::install(shapes),
+            "Shapes are installed before they're used."
         );
+    }
 
-        self.hitbox[1] = vec2(self.hitbox[3].x, self.hitbox[0].y);
-
-        for v in self.hitbox.iter_mut() {
-            v.rotate_mut(self.transform.rot);
-            *v += &self.transform.pos;
+    let mut replay_player = options.replay.as_ref().map(|path| {
+        let replay =
+            Replay::load(path).unwrap_or_else(|e| panic!("Couldn't load replay '{path}': {e}"));
+        ReplayPlayer::new(replay)
+    });
+    let mut session = options.net.as_ref().map(|net| connect(&options, net));
+    // Both peers start simulating once they're connected
+    let session_start = Instant::now();
+    // Online games live in the session, which is shown instead of `game`
+    let mut game = match (&options.load, &replay_player, options.seed) {
+        (Some(path), _, _) => {
+            save::load(path).unwrap_or_else(|e| panic!("Couldn't load save file '{path}': {e}"))
+        }
+        (None, Some(player), _) => player.start(),
+        (None, None, seed) if options.coop => {
+            let lives = options.lives.unwrap_or(DEFAULT_LIVES);
+            let mut game = Game::with_players(seed.unwrap_or_else(rand::random), 2, lives);
+            game.friendly_fire = options.friendly_fire;
+            game
         }
+        (None, None, seed) if options.versus.is_some() => Game::versus(
+            seed.unwrap_or_else(rand::random),
+            options.versus.unwrap_or(2),
+            options.lives.unwrap_or(DEFAULT_LIVES),
+            options.rounds.unwrap_or(DEFAULT_ROUNDS_TO_WIN),
+        ),
+        (None, None, seed) => Game::new(seed.unwrap_or_else(rand::random)),
+    };
+    if options.asteroid_physics.is_some() {
+        game.asteroid_physics = options.asteroid_physics;
     }
-}
-
-struct Asteroid {
-    transform: Transform<4>,
-    velocity: Vec2,
-}
-
-fn randf32(rng: &mut ThreadRng) -> f32 {
-    rng.gen::<f32>() * 2. - 1.
-}
-
-impl Asteroid {
-    fn random(rng: &mut ThreadRng, ship: &Ship) -> Self {
-        loop {
-            let velocity = vec2(randf32(rng) * 25., randf32(rng) * 25.);
-            let mut res = Self {
-                transform: Transform {
-                    rot: 0.,
-                    pos: Vec2 {
-                        x: if velocity.x >= 0. {
-                            rng.gen::<f32>() * DANGER_ZONE
-                        } else {
-                            CANVAS_WIDTH as f32 - (rng.gen::<f32>() * DANGER_ZONE)
-                        },
-                        y: if velocity.y >= 0. {
-                            rng.gen::<f32>() * DANGER_ZONE
-                        } else {
-                            CANVAS_HEIGHT as f32 - (rng.gen::<f32>() * DANGER_ZONE)
-                        },
-                    },
-                    vertices: [
-                        vec2(1. - randf32(rng), 1. - randf32(rng)).normalise(),
-                        vec2(1. - randf32(rng), -1. + randf32(rng)).normalise(),
-                        vec2(-1. + randf32(rng), -1. + randf32(rng)).normalise(),
-                        vec2(-1. + randf32(rng), 1. - randf32(rng)).normalise(),
-                    ],
-                    scale: 8. * (rng.gen::<f32>() + 1.),
-                    transform: [Vec2::ZERO, Vec2::ZERO, Vec2::ZERO, Vec2::ZERO],
-                },
-
-                velocity,
-            };
+    if options.power_ups {
+        game.power_ups = true;
+    }
+    if options.ballistics != Ballistics::default() {
+        game.ballistics = options.ballistics;
+    }
+    // Online every player uses the single player keys on their own computer
+    let local_player = session.as_ref().map_or(0, Session::local_player);
+    let key_maps: &[KeyMap] = if game.players.len() > 1 && session.is_none() {
+        &MULTIPLAYER_KEYS[..game.players.len().min(MULTIPLAYER_KEYS.len())]
+    } else {
+        std::slice::from_ref(&SINGLE_PLAYER_KEYS)
+    };
 
-            res.transform.apply();
+    let mut recorder = options.record.map(|target| {
+        Recorder::new(target, options.record_fps).expect("Couldn't start recording.")
+    });
 
-            let mut inside_ship = false;
-            inside_ship |= ship.hitbox.iter().any(|Vec2 { x, y }| res.contains(*x, *y));
-            inside_ship |= res.contains(ship.transform.pos.x, ship.transform.pos.y);
-            if !inside_ship {
-                return res;
-            }
-        }
+    #[cfg(feature = "scripting")]
+    let mut script = options.script.as_ref().map(|path| {
+        ScriptRules::load(path).unwrap_or_else(|e| panic!("Couldn't load script '{path}': {e}"))
+    });
+    #[cfg(not(feature = "scripting"))]
+    let mut script: Option<DefaultRules> = None;
+
+    if let Some(games) = options.bench_bot {
+        let rules: &mut dyn Rules = match &mut script {
+            Some(script) => script,
+            None => &mut DefaultRules,
+        };
+        let first_seed = options.seed.unwrap_or(0);
+        let report = bot::bench(
+            &Autopilot::default(),
+            games,
+            first_seed,
+            options.bench_time,
+            rules,
+        );
+        println!(
+            "Autopilot played {games} games (seeds {first_seed}..{}), {} survived {}s",
+            first_seed + games,
+            report.survived,
+            options.bench_time
+        );
+        println!(
+            "Score:    mean {:.1}, median {:.1}",
+            report.mean_score(),
+            report.median_score()
+        );
+        println!(
+            "Survival: mean {:.1}s, median {:.1}s",
+            report.mean_survival_time(),
+            report.median_survival_time()
+        );
+        return;
     }
 
-    fn contains(&self, x: f32, y: f32) -> bool {
-        let (mut left, mut right, mut top, mut bottom): (f32, f32, f32, f32) = (
-            self.transform.transform[0].x,
-            self.transform.transform[0].x,
-            self.transform.transform[0].y,
-            self.transform.transform[0].y,
+    if let (Some(headless_options), Some(session)) = (&options.headless, &mut session) {
+        let pilot = Autopilot::default();
+        net::run_headless(
+            session,
+            headless_options.frames as u32,
+            options.autopilot.then_some(&pilot),
         );
+        return;
+    }
 
-        for &Vec2 { x, y } in self.transform.transform.iter() {
-            left = left.min(x);
-            right = right.max(x);
-            top = top.min(y);
-            bottom = bottom.max(y);
+    if let Some(asteroids) = options.stress {
+        let seed = options.seed.unwrap_or(0);
+        println!("Asteroids  Bullets  Update ms  Grid ms  Brute force ms  Pairs");
+        let mut sizes: Vec<usize> = [8, 4, 2, 1]
+            .iter()
+            .map(|d| asteroids / d)
+            .filter(|n| *n > 0)
+            .collect();
+        sizes.dedup();
+        for size in sizes {
+            let report = stress::run(seed, size, STRESS_UPDATES);
+            println!(
+                "{:>9}  {:>7}  {:>9.3}  {:>7.3}  {:>14.3}  {:>5}",
+                report.asteroids,
+                report.bullets,
+                report.update_ms,
+                report.grid_query_ms,
+                report.brute_force_query_ms,
+                report.pairs
+            );
         }
-
-        (left..right).contains(&x) && (top..bottom).contains(&y)
+        return;
     }
-}
-
-struct Bullet {
-    pos: Vec2,
-    dir: Vec2,
-    wrap_count: u8
-}
 
-impl Bullet {
-    pub fn new(pos: Vec2, dir: Vec2) -> Self {
-        Self { pos, dir, wrap_count: 0 }
+    if let Some(headless_options) = &options.headless {
+        let rules: &mut dyn Rules = match &mut script {
+            Some(script) => script,
+            None => &mut DefaultRules,
+        };
+        headless::run(game, headless_options, replay_player, recorder, rules)
+            .expect("Couldn't export frames.");
+        #[cfg(feature = "scripting")]
+        for line in script.iter_mut().flat_map(ScriptRules::take_messages) {
+            println!("[Script] {line}");
+        }
+        return;
     }
-}
 
-fn main() {
-    let mut rng = rand::thread_rng();
-
-    let mut score = 0;
-    let mut high_score = 0;
-    let vertices = [
-        vec2(-1., -1.).normalise(),
-        vec2(1., -1.).normalise(),
-        vec2(0., 1.),
-    ];
-    let mut ship = Ship {
-        transform: Transform {
-            pos: vec2(CANVAS_WIDTH as f32 / 2., CANVAS_HEIGHT as f32 / 2.),
-            vertices: vertices.clone(),
-            scale: 10.,
-            rot: 0.,
-            transform: vertices,
-        },
-        velocity: vec2(0., 0.),
-        acc: DEFAULT_ACCELERATION,
-        hitbox: [vec2(0., 0.), vec2(0., 0.), vec2(0., 0.), vec2(0., 0.)],
-    };
-    ship.update(0.);
-
-    let mut asteroids = vec![Asteroid::random(&mut rng, &ship)];
-
-    let mut bullets: Vec<Bullet> = vec![];
-    let mut bullet_cooldown = DEFAULT_BULLET_COOLDOWN; // milliseconds
-    let mut last_bullet = Instant::now() - Duration::from_millis(bullet_cooldown);
+    let mut replay_recording = options.record_replay.as_ref().map(|_| Replay::new(&game));
+    // The console and quick-load change the game outside of the inputs, which
+    // the peer of an online game and a recorded replay can't follow
+    let outside_changes = session.is_none() && replay_recording.is_none();
+
+    let mut broadcaster = options.spectate.map(|port| {
+        let mut broadcaster = Broadcaster::bind(port)
+            .unwrap_or_else(|e| panic!("Couldn't broadcast on port {port}: {e}"));
+        // Viewers can't simulate mods or the predictions of an online game, they get the state instead
+        if script.is_some() || session.is_some() {
+            broadcaster.snapshot_interval = 0.;
+        }
+        println!("Broadcasting on port {port}, watch with asteroids_viewer");
+        broadcaster
+    });
+    // Set when the game changed outside of an update
+    let mut resync_viewers = false;
 
-    let mut show_hitbox = false;
+    let mut console = Console::default();
+    let mut debug_overlay = DebugOverlay::default();
+    let autopilot = Autopilot::default();
+    let mut autopilot_enabled = options.autopilot;
+    let mut reported_desyncs = 0;
 
     let mut last_redraw = Instant::now();
     let event_loop = EventLoop::new();
@@ -198,8 +331,15 @@ fn main() {
 
 Controls:
     Arrow Keys to move,
-    [X] to shoot,
+    [X] to shoot, hold [C] for the shield, [V] to switch weapons,
+    Co-op and Versus: Player 1 [WASD], [Space], [E] and [Q], Player 2 Arrow Keys, [Enter], [Right Shift] and [Right Ctrl],
+                      Player 3 [IJKL], [H], [U] and [Y], Player 4 Numpad [8456], [0], [7] and [9]
     (Debug) [Z] to show hitbox
+    (Debug) [F1] stats, [F2] frame times, [F3] velocities,
+            [F4] asteroid bounds, [F5] bullet wrap counts, [F7] collision grid
+    (Debug) [`] to open the console, type 'help' for a list of commands
+    [F6] to let the autopilot fly
+    (Debug) [F8] quick-save, [F9] quick-load
 
 Tips:
     * The orange-ish zone is the "Danger Zone", asteroids only spawn in the Danger Zone.
@@ -229,10 +369,32 @@ Good luck!"#
                         ..
                     },
             } if window_id == window.id() => match state {
-                ElementState::Pressed => pressed_keys[keycode as usize] = true,
+                ElementState::Pressed => {
+                    let repeat = pressed_keys[keycode as usize];
+                    pressed_keys[keycode as usize] = true;
+                    match keycode {
+                        VirtualKeyCode::Grave if !repeat && outside_changes => console.toggle(),
+                        VirtualKeyCode::Escape if console.open => console.toggle(),
+                        VirtualKeyCode::Up if console.open => console.recall(true),
+                        VirtualKeyCode::Down if console.open => console.recall(false),
+                        _ => (),
+                    }
+                }
                 ElementState::Released => pressed_keys[keycode as usize] = false,
             },
 
+            Event::WindowEvent {
+                window_id,
+                event: WindowEvent::ReceivedCharacter(c),
+            } if window_id == window.id() && console.open => {
+                let rules: &mut dyn Rules = match &mut script {
+                    Some(script) => script,
+                    None => &mut DefaultRules,
+                };
+                console.type_char(c, &mut game, rules);
+                resync_viewers = true;
+            }
+
             Event::MainEventsCleared => {
                 let now = Instant::now();
                 let frame_time = now - last_redraw;
@@ -240,183 +402,169 @@ Good luck!"#
 
                 if frame_time.as_micros() > min_frame_time {
                     let delta_time = frame_time.as_secs_f32();
-                    if pressed_keys[VirtualKeyCode::Z as usize]
-                        && !prev_pressed_keys[VirtualKeyCode::Z as usize] {
-                        show_hitbox = !show_hitbox;
-                    }
-
-                    if pressed_keys[VirtualKeyCode::Left as usize] {
-                        ship.transform.rot -= 3.5 * delta_time;
-                    }
-                    if pressed_keys[VirtualKeyCode::Right as usize] {
-                        ship.transform.rot += 3.5 * delta_time;
-                    }
-
-                    let (s, c) = (ship.transform.rot + FRAC_PI_2).sin_cos();
-
-                    let mut moving = false;
-                    if pressed_keys[VirtualKeyCode::Up as usize] {
-                        moving = true;
-                        ship.velocity.x += c * ship.acc * delta_time;
-                        ship.velocity.y += s * ship.acc * delta_time;
-                    }
-                    if pressed_keys[VirtualKeyCode::Down as usize] {
-                        moving = true;
-                        ship.velocity.x -= c * ship.acc * delta_time;
-                        ship.velocity.y -= s * ship.acc * delta_time;
-                    }
-
-                    let min_vel = 0.75;
-                    let acc_mul = 1. / 1.2;
-
-                    if !moving {
-                        if ship.velocity.x >= min_vel {
-                            ship.velocity.x -= ship.acc * acc_mul * delta_time;
-                        } else if ship.velocity.x <= -min_vel {
-                            ship.velocity.x += ship.acc * acc_mul * delta_time;
-                        } else {
-                            ship.velocity.x = 0.
-                        }
-
-                        if ship.velocity.y >= min_vel {
-                            ship.velocity.y -= ship.acc * acc_mul * delta_time;
-                        } else if ship.velocity.y <= -min_vel {
-                            ship.velocity.y += ship.acc * acc_mul * delta_time;
-                        } else {
-                            ship.velocity.y = 0.
+                    debug_overlay.record_frame(delta_time);
+
+                    // The game is paused while typing into the console
+                    if !console.open {
+                        let just_pressed = |key: VirtualKeyCode| {
+                            pressed_keys[key as usize] && !prev_pressed_keys[key as usize]
+                        };
+                        for (key, view) in [
+                            (VirtualKeyCode::Z, &mut debug_overlay.hitbox),
+                            (VirtualKeyCode::F1, &mut debug_overlay.stats),
+                            (VirtualKeyCode::F2, &mut debug_overlay.frame_graph),
+                            (VirtualKeyCode::F3, &mut debug_overlay.velocities),
+                            (VirtualKeyCode::F4, &mut debug_overlay.bounds),
+                            (VirtualKeyCode::F5, &mut debug_overlay.wrap_counts),
+                            (VirtualKeyCode::F7, &mut debug_overlay.grid),
+                        ] {
+                            if just_pressed(key) {
+                                *view = !*view;
+                            }
                         }
-                    }
 
-                    ship.update(delta_time);
-                    let horizontal_edge = (CANVAS_WIDTH - 1) as f32;
-                    if ship.transform.pos.x < 0. {
-                        ship.transform.pos.x = horizontal_edge;
-                    } else if ship.transform.pos.x > horizontal_edge {
-                        ship.transform.pos.x = 0.
-                    }
-
-                    let vertical_edge = (CANVAS_HEIGHT - 1) as f32;
-                    if ship.transform.pos.y < 0. {
-                        ship.transform.pos.y = vertical_edge;
-                    } else if ship.transform.pos.y > vertical_edge {
-                        ship.transform.pos.y = 0.
-                    }
-
-                    if pressed_keys[VirtualKeyCode::X as usize]
-                        && !prev_pressed_keys[VirtualKeyCode::X as usize]
-                        && (now - last_bullet).as_millis() as u64 >= bullet_cooldown
-                    {
-                        let dir = (ship.transform.rot + FRAC_PI_2).sin_cos();
-                        let dir = vec2(dir.0, dir.1);
-                        bullets.push(Bullet::new(vec2(
-                            ship.transform.pos.x + dir.y * ship.transform.scale,
-                            ship.transform.pos.y + dir.x * ship.transform.scale,
-                        ), dir));
-                        last_bullet = now;
-                    }
-
-                    bullets.retain_mut(|b| {
-                        b.pos.x += b.dir.y * 155. * delta_time;
-                        b.pos.y += b.dir.x * 155. * delta_time;
-                        if b.pos.x < 0. {
-                            b.pos.x = CANVAS_WIDTH as f32;
-                            b.wrap_count += 1;
-                        } else if b.pos.x > CANVAS_WIDTH as f32 {
-                            b.pos.x = 0.;
-                            b.wrap_count += 1;
+                        if just_pressed(VirtualKeyCode::F8) {
+                            let shown = session.as_ref().map_or(&game, Session::game);
+                            match save::save(shown, save::QUICKSAVE_PATH) {
+                                Ok(()) => println!("\n[Quick-save] Saved to {}", save::QUICKSAVE_PATH),
+                                Err(e) => println!("\n[Quick-save] Failed: {e}"),
+                            }
                         }
-                        if b.pos.y < 0. {
-                            b.pos.y = CANVAS_HEIGHT as f32;
-                            b.wrap_count += 1;
-                        } else if b.pos.y > CANVAS_HEIGHT as f32 {
-                            b.pos.y = 0.;
-                            b.wrap_count += 1;
+                        if just_pressed(VirtualKeyCode::F9) && outside_changes {
+                            match save::load(save::QUICKSAVE_PATH) {
+                                Ok(loaded) => {
+                                    game = loaded;
+                                    resync_viewers = true;
+                                }
+                                Err(e) => println!("\n[Quick-load] Failed: {e}"),
+                            }
                         }
 
-                        b.wrap_count < 5
-                    });
-
-                    if asteroids.is_empty() {
-                        for _ in 0..4 {
-                            asteroids.push(Asteroid::random(&mut rng, &ship))
+                        if just_pressed(VirtualKeyCode::F6) {
+                            autopilot_enabled = !autopilot_enabled;
+                            let state = if autopilot_enabled { "on" } else { "off" };
+                            println!("\n[Autopilot] {state}");
                         }
-                    }
-
-                    let mut ship_hit = false;
-                    let mut new_asteroids = vec![];
-                    asteroids.retain_mut(|asteroid| {
-                        asteroid.transform.pos.x += asteroid.velocity.x * delta_time;
-                        asteroid.transform.pos.y += asteroid.velocity.y * delta_time;
-                        asteroid.transform.apply();
 
-                        ship_hit |= ship.hitbox.iter().any(| Vec2 {x, y} | asteroid.contains(*x, *y));
-                        ship_hit |= asteroid.contains(ship.transform.pos.x, ship.transform.pos.y);
-
-                        let mut hit_index = 0;
-
-                        let bullet_hit = bullets.iter_mut().enumerate().any(|(i, b)| {
-                            let res = asteroid.contains(b.pos.x, b.pos.y);
-                            if res {
-                                hit_index = i;
+                        let replayed = replay_player.as_mut().and_then(ReplayPlayer::next_tick);
+                        if replay_player.is_some() && replayed.is_none() {
+                            println!("\n[Replay] Finished, you're in control now!");
+                            replay_player = None;
+                        }
+                        let (inputs, delta_time) = replayed.unwrap_or_else(|| {
+                            let mut inputs: Vec<Input> = key_maps
+                                .iter()
+                                .map(|keys| Input {
+                                    left: pressed_keys[keys.left as usize],
+                                    right: pressed_keys[keys.right as usize],
+                                    thrust: pressed_keys[keys.thrust as usize],
+                                    reverse: pressed_keys[keys.reverse as usize],
+                                    fire: just_pressed(keys.fire),
+                                    shield: pressed_keys[keys.shield as usize],
+                                    switch_weapon: just_pressed(keys.switch_weapon),
+                                })
+                                .collect();
+                            // The autopilot flies the first or the local ship
+                            if autopilot_enabled {
+                                let shown = session.as_ref().map_or(&game, Session::game);
+                                inputs[0] = autopilot.act_as(shown, local_player, delta_time);
                             }
-                            res
+                            (inputs, delta_time)
                         });
-
-                        if bullet_hit {
-                            bullets.swap_remove(hit_index);
-                            score += 1;
-                            ship.acc += (score as f32) / 32.;
-                            if score % 5 == 0 {
-                                bullet_cooldown -= 200;
-                                bullet_cooldown = bullet_cooldown.max(700);
+                        #[cfg(feature = "scripting")]
+                        if let Some(script) = &mut script {
+                            script.reload_if_changed();
+                        }
+                        let rules: &mut dyn Rules = match &mut script {
+                            Some(script) => script,
+                            None => &mut DefaultRules,
+                        };
+                        // Events of every tick an online game advanced by
+                        let mut online_events = vec![];
+                        match &mut session {
+                            // Online games advance in fixed ticks at real time, however long frames take
+                            Some(session) => {
+                                let due = (session_start.elapsed().as_secs_f32() / FIXED_DELTA_TIME) as u32;
+                                let mut input = inputs[0];
+                                while session.tick() < due && session.advance(&input) {
+                                    online_events.extend(session.game().events.iter().copied());
+                                    // A key press fires once
+                                    input.fire = false;
+                                }
+                                session.poll();
+                                for desync in session.desyncs().iter().skip(reported_desyncs) {
+                                    println!("\n[Online] {desync}");
+                                }
+                                reported_desyncs = session.desyncs().len();
                             }
-                            // TODO remove later
-                            println!("\n[Explosion Sounds] Score: {score}");
-
-                            let n = rng.gen_range(1..=3);
-                            for _ in 0..n {
-                                new_asteroids.push(Asteroid {
-                                    transform: Transform {
-                                        scale: asteroid.transform.scale / ((rng.gen::<f32>() * 2.) + 1.),
-                                        rot: randf32(&mut rng) * PI * 2.,
-                                        ..asteroid.transform.clone()
-                                    },
-                                    velocity: vec2(randf32(&mut rng), randf32(&mut rng)).normalise() * 25.,
-                                });
+                            None => game.update_players(&inputs, delta_time, rules),
+                        }
+                        #[cfg(feature = "scripting")]
+                        for line in script.iter_mut().flat_map(ScriptRules::take_messages) {
+                            println!("\n[Script] {line}");
+                            console.print(line);
+                        }
+                        let (shown, events) = match &session {
+                            Some(session) => (session.game(), &online_events),
+                            None => (&game, &game.events),
+                        };
+                        for event in events {
+                            match event {
+                                // TODO remove later
+                                GameEvent::AsteroidDestroyed { score, .. } => {
+                                    println!("\n[Explosion Sounds] Score: {score}")
+                                }
+                                // TODO remove later
+                                GameEvent::LifeLost {
+                                    player,
+                                    lives,
+                                    killer,
+                                } => match killer {
+                                    Some(killer) => println!(
+                                        "\n[Ship Explosion] Player {} shot down Player {}! Lives left: {lives}",
+                                        killer + 1,
+                                        player + 1
+                                    ),
+                                    None => println!(
+                                        "\n[Ship Explosion] Player {} crashed! Lives left: {lives}",
+                                        player + 1
+                                    ),
+                                },
+                                GameEvent::RoundOver { round, winner } => match winner {
+                                    Some(winner) => println!("\n[Versus] Player {} wins round {round}!", winner + 1),
+                                    None => println!("\n[Versus] Round {round} is a draw!"),
+                                },
+                                GameEvent::MatchWon { player } => {
+                                    println!("\n[Versus] Player {} wins the match!", player + 1)
+                                }
+                                GameEvent::PowerUpCollected { player, kind } => {
+                                    println!("\n[Power-Up] Player {} collected {}", player + 1, kind.name())
+                                }
+                                // TODO remove later
+                                GameEvent::ShipCrashed { score, high_score } => println!(
+                                    "\n[Ship Explosion] You crashed! Score: {score}, High Score: {high_score}"
+                                ),
                             }
                         }
 
-                        if asteroid.transform.pos.x < 0. {
-                            asteroid.transform.pos.x = CANVAS_WIDTH as f32
-                        } else if asteroid.transform.pos.x > CANVAS_WIDTH as f32 {
-                            asteroid.transform.pos.x = 0.
+                        if let Some(broadcaster) = &mut broadcaster {
+                            if resync_viewers {
+                                broadcaster.snapshot(shown);
+                                resync_viewers = false;
+                            } else {
+                                broadcaster.update(shown, &inputs, delta_time);
+                            }
+                        }
+                        if let Some(replay) = &mut replay_recording {
+                            replay.record(&inputs, delta_time);
                         }
-                        if asteroid.transform.pos.y < 0. {
-                            asteroid.transform.pos.y = CANVAS_HEIGHT as f32
-                        } else if asteroid.transform.pos.y > CANVAS_HEIGHT as f32 {
-                            asteroid.transform.pos.y = 0.
+                        let captured = recorder
+                            .as_mut()
+                            .map(|r| r.capture(shown, &debug_overlay, delta_time));
+                        if let Some(Err(e)) = captured {
+                            println!("\n[Recording] Stopped: {e}");
+                            recorder = None;
                         }
-                        
-                        !bullet_hit && asteroid.transform.scale > 3.
-                    });
-
-                    asteroids.extend(new_asteroids);
-
-                    if ship_hit {
-                        high_score = high_score.max(score);
-                        // TODO remove later
-                        println!("\n[Ship Explosion] You crashed! Score: {score}, High Score: {high_score}");
-                        asteroids.clear();
-                        asteroids.push(Asteroid::random(&mut rng, &ship));
-                        bullets.clear();
-                        bullet_cooldown = DEFAULT_BULLET_COOLDOWN;
-                        last_bullet = now - Duration::from_millis(bullet_cooldown);
-                        ship.transform.pos.x = (CANVAS_WIDTH / 2) as f32;
-                        ship.transform.pos.y = (CANVAS_HEIGHT / 2) as f32;
-                        ship.acc = DEFAULT_ACCELERATION;
-                        ship.velocity = vec2(0., 0.);
-                        ship.transform.rot = FRAC_PI_2 * 2.;
-                        score = 0;
                     }
 
                     last_redraw = now;
@@ -442,60 +590,13 @@ Good luck!"#
                         (width as usize, height as usize),
                         (CANVAS_WIDTH, CANVAS_HEIGHT),
                     );
-                    canvas.fill(0);
-
-                    let danger_zone_color = RGBu32::Rgb(40, 15, 0);
-                    canvas.rect(0, 0, CANVAS_WIDTH, DANGER_ZONE as usize, &danger_zone_color);
-                    canvas.rect(0, CANVAS_HEIGHT as i32 - DANGER_ZONE as i32, CANVAS_WIDTH, DANGER_ZONE as usize, &danger_zone_color);
-                    canvas.rect(0, 0, DANGER_ZONE as usize, CANVAS_HEIGHT, &danger_zone_color);
-                    canvas.rect(CANVAS_WIDTH as i32 - DANGER_ZONE as i32, 0, DANGER_ZONE as usize, CANVAS_HEIGHT, &danger_zone_color);
-
-                    for asteroid in &asteroids {
-                        for (i, v) in asteroid.transform.transform.iter().enumerate() {
-                            if i > 0 {
-                                canvas.line(
-                                    v.x as i32,
-                                    v.y as i32,
-                                    asteroid.transform.transform[i - 1].x as i32,
-                                    asteroid.transform.transform[i - 1].y as i32,
-                                    &GREEN,
-                                )
-                            } else {
-                                let len = asteroid.transform.transform.len();
-                                canvas.line(
-                                    v.x as i32,
-                                    v.y as i32,
-                                    asteroid.transform.transform[len - 1].x as i32,
-                                    asteroid.transform.transform[len - 1].y as i32,
-                                    &GREEN,
-                                )
-                            }
-                        }
-                    }
-                    ship.transform.apply();
-                    for &Vec2 { x: x0, y: y0 } in ship.transform.transform.iter() {
-                        for &Vec2 { x: x1, y: y1 } in ship.transform.transform.iter() {
-                            canvas.line(x0 as i32, y0 as i32, x1 as i32, y1 as i32, &RED);
-                        }
-                    }
+                    let shown = session.as_ref().map_or(&game, Session::game);
+                    render::draw(&mut canvas, shown, &debug_overlay);
 
-                    for Bullet { pos, .. } in bullets.iter() {
-                        canvas.put(pos.x as i32, pos.y as i32, &BULLET_COLOR)
+                    if console.open {
+                        console.draw(&mut canvas);
                     }
 
-                    if show_hitbox {
-                        for &Vec2 { x: x0, y: y0 } in ship.hitbox.iter() {
-                            for &Vec2 { x: x1, y: y1 } in ship.hitbox.iter() {
-                                canvas.line(
-                                    x0 as i32,
-                                    y0 as i32,
-                                    x1 as i32,
-                                    y1 as i32,
-                                    &YELLOW,
-                                );
-                            }
-                        }
-                    }
                     buffer.present().expect("Couldn't present frame buffer.");
                 }
             }
@@ -504,6 +605,19 @@ Good luck!"#
                 window_id: id,
                 event: WindowEvent::CloseRequested,
             } if id == window.id() => {
+                // The event loop never returns, so recordings have to be written out here
+                if let Some(recorder) = recorder.take() {
+                    match recorder.finish() {
+                        Ok(frames) => println!("\n[Recording] Saved {frames} frames"),
+                        Err(e) => println!("\n[Recording] Failed: {e}"),
+                    }
+                }
+                if let (Some(replay), Some(path)) = (replay_recording.take(), &options.record_replay) {
+                    match replay.save(path) {
+                        Ok(()) => println!("\n[Replay] Saved to {path}"),
+                        Err(e) => println!("\n[Replay] Failed: {e}"),
+                    }
+                }
                 *control_flow = ControlFlow::Exit;
             }
 
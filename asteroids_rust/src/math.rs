@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 use std::ops::{Add, AddAssign, Mul, MulAssign};
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -82,6 +84,20 @@ impl Vec2 {
         self.y = y;
     }
 
+    pub fn dot(&self, other: &Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the 3D cross product, positive if `other` is
+    /// counterclockwise of `self` in the direction [`Vec2::rotate`] turns.
+    pub fn cross(&self, other: &Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn length(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
     pub fn normalise(mut self) -> Self {
         self.normalise_mut();
         self
@@ -95,16 +111,84 @@ impl Vec2 {
     }
 }
 
-#[derive(Clone)]
+/// An outline with a vertex count fixed at compile time, e.g. a triangle. See [`Polygon`] for outlines that vary at runtime.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Transform<const N: usize> {
     pub pos: Vec2,
+    #[serde(with = "vertex_array")]
     pub vertices: [Vec2; N],
+    #[serde(with = "vertex_array")]
     pub transform: [Vec2; N],
     pub scale: f32,
     pub rot: f32,
 }
 
 impl<const N: usize> Transform<N> {
+    /// The outline as of the last [`Transform::apply`].
+    pub fn transformed(&self) -> &[Vec2] {
+        &self.transform
+    }
+
+    pub fn apply(&mut self) {
+        for (i, v) in self.vertices.iter().enumerate() {
+            self.transform[i] = v.clone().rotate(self.rot) * self.scale;
+            self.transform[i].x += self.pos.x;
+            self.transform[i].y += self.pos.y;
+        }
+    }
+}
+
+/// Vertices a [`Polygon`] stores inline, more are kept on the heap.
+pub const INLINE_VERTICES: usize = 16;
+
+type Vertices = SmallVec<[Vec2; INLINE_VERTICES]>;
+
+/// Like [`Transform`], but the number of vertices is chosen at runtime, so
+/// entities with different outlines fit in one collection. Up to
+/// [`INLINE_VERTICES`] vertices are stored inline, so cloning such a polygon
+/// doesn't allocate.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "PolygonData", into = "PolygonData")]
+pub struct Polygon {
+    pub pos: Vec2,
+    pub scale: f32,
+    pub rot: f32,
+    vertices: Vertices,
+    transform: Vertices,
+}
+
+impl Polygon {
+    /// A polygon with the outline `vertices`, already applied.
+    ///
+    /// # Panics
+    /// If there are fewer than 3 vertices.
+    pub fn new(pos: Vec2, vertices: &[Vec2], scale: f32, rot: f32) -> Self {
+        assert!(
+            vertices.len() >= 3,
+            "a polygon needs at least 3 vertices, not {}",
+            vertices.len()
+        );
+        let mut polygon = Self {
+            pos,
+            scale,
+            rot,
+            vertices: vertices.iter().cloned().collect(),
+            transform: vertices.iter().cloned().collect(),
+        };
+        polygon.apply();
+        polygon
+    }
+
+    /// The outline before it is scaled, rotated and moved.
+    pub fn vertices(&self) -> &[Vec2] {
+        &self.vertices
+    }
+
+    /// The outline as of the last [`Polygon::apply`].
+    pub fn transformed(&self) -> &[Vec2] {
+        &self.transform
+    }
+
     pub fn apply(&mut self) {
         for (i, v) in self.vertices.iter().enumerate() {
             self.transform[i] = v.clone().rotate(self.rot) * self.scale;
@@ -113,3 +197,262 @@ impl<const N: usize> Transform<N> {
         }
     }
 }
+
+impl<const N: usize> From<Transform<N>> for Polygon {
+    fn from(transform: Transform<N>) -> Self {
+        Self::new(
+            transform.pos,
+            &transform.vertices,
+            transform.scale,
+            transform.rot,
+        )
+    }
+}
+
+/// How a [`Polygon`] is (de)serialized, the transformed outline is recomputed when loading.
+#[derive(Clone, Serialize, Deserialize)]
+struct PolygonData {
+    pos: Vec2,
+    vertices: Vertices,
+    scale: f32,
+    rot: f32,
+}
+
+impl TryFrom<PolygonData> for Polygon {
+    type Error = String;
+
+    fn try_from(data: PolygonData) -> Result<Self, String> {
+        if data.vertices.len() < 3 {
+            return Err(format!(
+                "a polygon needs at least 3 vertices, not {}",
+                data.vertices.len()
+            ));
+        }
+        Ok(Self::new(data.pos, &data.vertices, data.scale, data.rot))
+    }
+}
+
+impl From<Polygon> for PolygonData {
+    fn from(polygon: Polygon) -> Self {
+        Self {
+            pos: polygon.pos,
+            vertices: polygon.vertices,
+            scale: polygon.scale,
+            rot: polygon.rot,
+        }
+    }
+}
+
+/// Shortest signed distance from 0 to `d` on an axis of length `size` that wraps around.
+pub fn wrap_delta(d: f32, size: f32) -> f32 {
+    let d = d.rem_euclid(size);
+    if d > size / 2. {
+        d - size
+    } else {
+        d
+    }
+}
+
+/// serde only implements its traits for arrays up to a fixed length, so
+/// `[Vec2; N]` is (de)serialized as a sequence of exactly N elements.
+mod vertex_array {
+    use super::Vec2;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        vertices: &[Vec2; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        vertices.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[Vec2; N], D::Error> {
+        let vertices = Vec::<Vec2>::deserialize(deserializer)?;
+        let len = vertices.len();
+        vertices
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &N.to_string().as_str()))
+    }
+}
+
+/// Where a circle overlaps a polygon, see [`circle_polygon_contact`].
+#[derive(Clone)]
+pub struct Contact {
+    /// Unit vector from the polygon towards the centre of the circle.
+    pub normal: Vec2,
+    /// Distance the circle has to move along `normal` to stop overlapping.
+    pub depth: f32,
+}
+
+/// Closest point to `p` on the segment from `a` to `b`.
+fn closest_on_segment(p: &Vec2, a: &Vec2, b: &Vec2) -> Vec2 {
+    let ab = vec2(b.x - a.x, b.y - a.y);
+    let length_squared = ab.dot(&ab);
+    let t = if length_squared > 0. {
+        (vec2(p.x - a.x, p.y - a.y).dot(&ab) / length_squared).clamp(0., 1.)
+    } else {
+        0.
+    };
+    vec2(a.x + ab.x * t, a.y + ab.y * t)
+}
+
+/// Whether `p` is inside the closed outline through `points`.
+pub fn polygon_contains(points: &[Vec2], p: &Vec2) -> bool {
+    let mut inside = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + points.len() - 1) % points.len()];
+        // Counts the edges a ray from `p` to the right crosses
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// How the circle at `centre` with `radius` overlaps the closed outline
+/// through `points`, `None` if they don't touch. Also finds circles entirely
+/// inside the outline.
+pub fn circle_polygon_contact(centre: &Vec2, radius: f32, points: &[Vec2]) -> Option<Contact> {
+    let mut closest = None;
+    let mut distance_squared = f32::MAX;
+    for (i, a) in points.iter().enumerate() {
+        let point = closest_on_segment(centre, a, &points[(i + 1) % points.len()]);
+        let offset = vec2(centre.x - point.x, centre.y - point.y);
+        if offset.dot(&offset) < distance_squared {
+            distance_squared = offset.dot(&offset);
+            closest = Some(offset);
+        }
+    }
+    let offset = closest?;
+    let distance = distance_squared.sqrt();
+    let inside = polygon_contains(points, centre);
+    if !inside && distance >= radius {
+        return None;
+    }
+    // A centre right on the outline has no direction to it
+    let normal = if distance > f32::EPSILON {
+        vec2(offset.x / distance, offset.y / distance)
+    } else {
+        vec2(1., 0.)
+    };
+    Some(if inside {
+        Contact {
+            normal: normal * -1.,
+            depth: radius + distance,
+        }
+    } else {
+        Contact {
+            normal,
+            depth: radius - distance,
+        }
+    })
+}
+
+/// Distance along the ray from `origin` in the unit direction `dir` to the
+/// closed outline through `points`, `None` if the ray misses it. A ray that
+/// starts inside the outline hits it at 0.
+pub fn ray_polygon_distance(origin: &Vec2, dir: &Vec2, points: &[Vec2]) -> Option<f32> {
+    if polygon_contains(points, origin) {
+        return Some(0.);
+    }
+    let mut nearest: Option<f32> = None;
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        let edge = vec2(b.x - a.x, b.y - a.y);
+        let denominator = dir.cross(&edge);
+        // Parallel to the edge
+        if denominator.abs() <= f32::EPSILON {
+            continue;
+        }
+        let to_edge = vec2(a.x - origin.x, a.y - origin.y);
+        let t = to_edge.cross(&edge) / denominator;
+        let u = to_edge.cross(dir) / denominator;
+        if t >= 0. && (0. ..=1.).contains(&u) && nearest.is_none_or(|n| t < n) {
+            nearest = Some(t);
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The square from (0, 0) to (10, 10).
+    fn square() -> [Vec2; 4] {
+        [vec2(0., 0.), vec2(10., 0.), vec2(10., 10.), vec2(0., 10.)]
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-4, "{a} != {b}");
+    }
+
+    #[test]
+    fn circle_overlapping_an_edge_is_pushed_out_through_it() {
+        let contact = circle_polygon_contact(&vec2(12., 5.), 3., &square())
+            .expect("the circle touches the square");
+        assert_close(contact.normal.x, 1.);
+        assert_close(contact.normal.y, 0.);
+        assert_close(contact.depth, 1.);
+    }
+
+    #[test]
+    fn circle_overlapping_a_corner_is_pushed_out_diagonally() {
+        let contact = circle_polygon_contact(&vec2(11., 11.), 2., &square())
+            .expect("the circle touches the corner");
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        assert_close(contact.normal.x, diagonal);
+        assert_close(contact.normal.y, diagonal);
+        assert_close(contact.depth, 2. - 2_f32.sqrt());
+    }
+
+    #[test]
+    fn circle_inside_the_outline_is_pushed_out_through_the_nearest_edge() {
+        let contact = circle_polygon_contact(&vec2(5., 2.), 1., &square())
+            .expect("the circle is inside the square");
+        assert_close(contact.normal.x, 0.);
+        assert_close(contact.normal.y, -1.);
+        assert_close(contact.depth, 3.);
+    }
+
+    #[test]
+    fn circle_apart_from_the_outline_has_no_contact() {
+        assert!(circle_polygon_contact(&vec2(14., 5.), 3., &square()).is_none());
+        // Just touching isn't overlapping
+        assert!(circle_polygon_contact(&vec2(13., 5.), 3., &square()).is_none());
+        assert!(circle_polygon_contact(&vec2(13., 13.), 4., &square()).is_none());
+    }
+
+    #[test]
+    fn ray_hits_the_nearest_edge() {
+        assert_close(
+            ray_polygon_distance(&vec2(-5., 5.), &vec2(1., 0.), &square()).unwrap(),
+            5.,
+        );
+        assert_close(
+            ray_polygon_distance(&vec2(5., 20.), &vec2(0., -1.), &square()).unwrap(),
+            10.,
+        );
+        let diagonal = vec2(1., 1.).normalise();
+        let distance = ray_polygon_distance(&vec2(-2., -2.), &diagonal, &square()).unwrap();
+        assert_close(distance, 2. * 2_f32.sqrt());
+    }
+
+    #[test]
+    fn ray_starting_inside_hits_at_once() {
+        assert_eq!(
+            ray_polygon_distance(&vec2(5., 5.), &vec2(1., 0.), &square()),
+            Some(0.)
+        );
+    }
+
+    #[test]
+    fn ray_missing_the_outline_has_no_distance() {
+        // Pointing away and passing beside it on two sides
+        assert!(ray_polygon_distance(&vec2(-5., 5.), &vec2(-1., 0.), &square()).is_none());
+        assert!(ray_polygon_distance(&vec2(-5., 15.), &vec2(1., 0.), &square()).is_none());
+        assert!(ray_polygon_distance(&vec2(15., -1.), &vec2(0., 1.), &square()).is_none());
+    }
+}
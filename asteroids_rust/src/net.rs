@@ -0,0 +1,972 @@
+//! Peer-to-peer online play over UDP with input delay and rollback.
+//!
+//! Both peers run the same deterministic simulation at [`FIXED_DELTA_TIME`]
+//! and only exchange inputs. A local input is scheduled
+//! [`SessionConfig::input_delay`] ticks ahead so it usually reaches the peer
+//! in time. Remote inputs that haven't arrived yet are predicted to repeat the
+//! last known one, and when the real input turns out to differ the session
+//! restores the snapshot from before that tick and simulates forward again.
+//! Every [`CHECKSUM_INTERVAL`] ticks the peers compare a checksum of the
+//! confirmed game state to detect desyncs.
+//!
+//! Packets are little endian and start with [`MAGIC`], [`PROTOCOL_VERSION`]
+//! and a kind byte:
+//!  * `HELLO`: u8 player, u8 flags (1: the settings were received),
+//!    u64 seed, u8 versus, u32 lives, u32 rounds to win, u8 asteroid
+//!    physics, f32 restitution, u8 power-ups, f32 bullet range (0 for
+//!    screen wraps), u8 inherit velocity, u32 max bullets (0 for no limit)
+//!  * `INPUTS`: u32 inputs received from the peer, u32 current tick,
+//!    i8 frame advantage, u32 checksum tick, u64 checksum, u32 first tick,
+//!    u8 count and one input byte per tick. Inputs the peer hasn't
+//!    acknowledged are sent again with every packet, so lost packets only
+//!    cost time.
+
+use crate::bot::Autopilot;
+use crate::game::{Game, Input, FIXED_DELTA_TIME};
+use crate::physics::AsteroidPhysics;
+use crate::rules::DefaultRules;
+use crate::weapon::Ballistics;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+pub const MAGIC: &[u8; 4] = b"ASTN";
+pub const PROTOCOL_VERSION: u8 = 4;
+/// Ticks between two state checksums.
+pub const CHECKSUM_INTERVAL: u32 = 30;
+/// Seconds between two hello packets while connecting.
+const HELLO_INTERVAL: f64 = 0.1;
+
+const HELLO: u8 = 0;
+const INPUTS: u8 = 1;
+const NO_CHECKSUM: u32 = u32::MAX;
+
+/// Sends and receives whole packets, unreliably and unordered like UDP.
+pub trait Transport {
+    fn send(&mut self, packet: &[u8]);
+    /// The next received packet, `None` if there is none right now.
+    fn recv(&mut self) -> Option<Vec<u8>>;
+}
+
+/// A non-blocking UDP socket talking to a single peer.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    /// Learned from the first packet when hosting.
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// Waits for a peer on `port`.
+    pub fn host(port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+        socket.set_nonblocking(true)?;
+        Ok(Self { socket, peer: None })
+    }
+
+    /// Talks to the peer at `address`, e.g. `127.0.0.1:7000`.
+    pub fn join(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let peer = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to join"))?;
+        let local: SocketAddr = if peer.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        }
+        .parse()
+        .unwrap();
+        let socket = UdpSocket::bind(local)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            peer: Some(peer),
+        })
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, packet: &[u8]) {
+        if let Some(peer) = self.peer {
+            // Lost packets are expected, errors are treated the same way
+            let _ = self.socket.send_to(packet, peer);
+        }
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        let mut buf = [0; 2048];
+        loop {
+            // Errors like an ICMP "port unreachable" before the peer is up look like no packet
+            let (len, from) = self.socket.recv_from(&mut buf).ok()?;
+            match self.peer {
+                None => self.peer = Some(from),
+                Some(peer) if peer != from => continue,
+                Some(_) => (),
+            }
+            return Some(buf[..len].to_vec());
+        }
+    }
+}
+
+/// Settings of [`LossyTransport`].
+#[derive(Clone, Copy, Default)]
+pub struct NetConditions {
+    /// Fraction of packets that are dropped.
+    pub loss: f64,
+    pub latency: Duration,
+    /// Random extra latency of up to this much, which also reorders packets.
+    pub jitter: Duration,
+}
+
+/// Simulates a bad connection by dropping and delaying outgoing packets.
+pub struct LossyTransport<T> {
+    inner: T,
+    conditions: NetConditions,
+    rng: Pcg32,
+    /// Packets waiting for their latency to pass.
+    queue: Vec<(Instant, Vec<u8>)>,
+}
+
+impl<T: Transport> LossyTransport<T> {
+    pub fn new(inner: T, conditions: NetConditions, seed: u64) -> Self {
+        Self {
+            inner,
+            conditions,
+            rng: Pcg32::seed_from_u64(seed),
+            queue: vec![],
+        }
+    }
+
+    fn flush(&mut self) {
+        let now = Instant::now();
+        let mut i = 0;
+        while i < self.queue.len() {
+            if self.queue[i].0 <= now {
+                let (_, packet) = self.queue.swap_remove(i);
+                self.inner.send(&packet);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl<T: Transport> Transport for LossyTransport<T> {
+    fn send(&mut self, packet: &[u8]) {
+        if self.rng.gen::<f64>() >= self.conditions.loss {
+            let delay = self.conditions.latency + self.conditions.jitter.mul_f64(self.rng.gen());
+            self.queue.push((Instant::now() + delay, packet.to_vec()));
+        }
+        self.flush();
+    }
+
+    fn recv(&mut self) -> Option<Vec<u8>> {
+        self.flush();
+        self.inner.recv()
+    }
+}
+
+/// What the host decides about the game, sent to the other peer when connecting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchSettings {
+    pub seed: u64,
+    /// A versus match instead of co-op.
+    pub versus: bool,
+    pub lives: u32,
+    /// Only used for versus matches.
+    pub rounds_to_win: u32,
+    pub asteroid_physics: Option<AsteroidPhysics>,
+    pub power_ups: bool,
+    pub ballistics: Ballistics,
+}
+
+impl MatchSettings {
+    /// The two player game both peers start from.
+    pub fn new_game(&self) -> Game {
+        let mut game = if self.versus {
+            Game::versus(self.seed, 2, self.lives, self.rounds_to_win)
+        } else {
+            Game::with_players(self.seed, 2, self.lives)
+        };
+        game.asteroid_physics = self.asteroid_physics;
+        game.power_ups = self.power_ups;
+        game.ballistics = self.ballistics;
+        game
+    }
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    /// The peer didn't answer in time.
+    Timeout,
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "{e}"),
+            NetError::Timeout => write!(f, "the other player didn't answer"),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> Self {
+        NetError::Io(e)
+    }
+}
+
+enum Packet {
+    Hello {
+        player: u8,
+        acknowledged: bool,
+        settings: MatchSettings,
+    },
+    Inputs {
+        ack: u32,
+        tick: u32,
+        advantage: i8,
+        checksum: Option<(u32, u64)>,
+        first: u32,
+        inputs: Vec<u8>,
+    },
+}
+
+impl Packet {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        out.push(PROTOCOL_VERSION);
+        match self {
+            Packet::Hello {
+                player,
+                acknowledged,
+                settings,
+            } => {
+                out.extend([HELLO, *player, *acknowledged as u8]);
+                out.extend(settings.seed.to_le_bytes());
+                out.push(settings.versus as u8);
+                out.extend(settings.lives.to_le_bytes());
+                out.extend(settings.rounds_to_win.to_le_bytes());
+                out.push(settings.asteroid_physics.is_some() as u8);
+                let restitution = settings
+                    .asteroid_physics
+                    .map_or(0., |physics| physics.restitution);
+                out.extend(restitution.to_le_bytes());
+                out.push(settings.power_ups as u8);
+                let ballistics = &settings.ballistics;
+                out.extend(ballistics.range.unwrap_or(0.).to_le_bytes());
+                out.push(ballistics.inherit_velocity as u8);
+                out.extend(ballistics.max_bullets.unwrap_or(0).to_le_bytes());
+            }
+            Packet::Inputs {
+                ack,
+                tick,
+                advantage,
+                checksum,
+                first,
+                inputs,
+            } => {
+                out.push(INPUTS);
+                out.extend(ack.to_le_bytes());
+                out.extend(tick.to_le_bytes());
+                out.extend(advantage.to_le_bytes());
+                let (checksum_tick, checksum) = checksum.unwrap_or((NO_CHECKSUM, 0));
+                out.extend(checksum_tick.to_le_bytes());
+                out.extend(checksum.to_le_bytes());
+                out.extend(first.to_le_bytes());
+                out.push(inputs.len() as u8);
+                out.extend(inputs);
+            }
+        }
+        out
+    }
+
+    /// `None` for packets of other programs or protocol versions and truncated packets.
+    fn decode(packet: &[u8]) -> Option<Self> {
+        let mut r = Reader(packet);
+        if r.take(4)? != MAGIC || r.u8()? != PROTOCOL_VERSION {
+            return None;
+        }
+        match r.u8()? {
+            HELLO => Some(Packet::Hello {
+                player: r.u8()?,
+                acknowledged: r.u8()? != 0,
+                settings: MatchSettings {
+                    seed: r.u64()?,
+                    versus: r.u8()? != 0,
+                    lives: r.u32()?,
+                    rounds_to_win: r.u32()?,
+                    asteroid_physics: {
+                        let enabled = r.u8()? != 0;
+                        let restitution = r.f32()?;
+                        enabled.then_some(AsteroidPhysics { restitution })
+                    },
+                    power_ups: r.u8()? != 0,
+                    ballistics: Ballistics {
+                        range: Some(r.f32()?).filter(|range| *range > 0.),
+                        inherit_velocity: r.u8()? != 0,
+                        max_bullets: Some(r.u32()?).filter(|max| *max > 0),
+                    },
+                },
+            }),
+            INPUTS => {
+                let ack = r.u32()?;
+                let tick = r.u32()?;
+                let advantage = r.u8()? as i8;
+                let checksum_tick = r.u32()?;
+                let checksum = r.u64()?;
+                let first = r.u32()?;
+                let count = r.u8()? as usize;
+                Some(Packet::Inputs {
+                    ack,
+                    tick,
+                    advantage,
+                    checksum: (checksum_tick != NO_CHECKSUM).then_some((checksum_tick, checksum)),
+                    first,
+                    inputs: r.take(count)?.to_vec(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
+
+/// FNV-1a hash of the whole game state, equal on both peers as long as
+/// their simulations agree.
+pub fn checksum(game: &Game) -> u64 {
+    let bytes = serde_json::to_vec(game).expect("game state is always serializable");
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Two checksums of the same tick that don't match.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Desync {
+    pub tick: u32,
+    pub local: u64,
+    pub remote: u64,
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "desync at tick {}: local checksum {:016x}, remote {:016x}",
+            self.tick, self.local, self.remote
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SessionConfig {
+    /// Ticks between reading a local input and simulating it.
+    pub input_delay: u32,
+    /// Ticks the session may run ahead of the last confirmed remote input
+    /// before it waits for the peer.
+    pub max_rollback: u32,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            input_delay: 2,
+            max_rollback: 8,
+        }
+    }
+}
+
+/// A two player game kept in sync with a peer, see the module documentation.
+pub struct Session<T> {
+    transport: T,
+    config: SessionConfig,
+    /// Index of the local player in `game.players`.
+    local: usize,
+    game: Game,
+    /// The tick the next update simulates.
+    tick: u32,
+    /// Local inputs by tick, including the input delay.
+    local_inputs: Vec<u8>,
+    /// Remote inputs by tick, only as far as they arrived without gaps.
+    remote_inputs: Vec<u8>,
+    /// The remote input each simulated tick used, predicted or not.
+    used_remote_inputs: Vec<u8>,
+    /// Game states before the ticks `snapshot_tick..tick`.
+    snapshots: VecDeque<Game>,
+    snapshot_tick: u32,
+    /// Ticks before this are simulated with confirmed inputs on both sides.
+    confirmed: u32,
+    /// Number of local inputs the peer received.
+    remote_ack: u32,
+    remote_tick: u32,
+    remote_advantage: i8,
+    last_checksum: Option<(u32, u64)>,
+    local_checksums: HashMap<u32, u64>,
+    remote_checksums: HashMap<u32, u64>,
+    checksums_compared: u32,
+    desyncs: Vec<Desync>,
+    rollbacks: u32,
+    resimulated_ticks: u32,
+}
+
+impl<T: Transport> Session<T> {
+    /// Waits up to `timeout` for a peer that joins, then starts `settings`
+    /// with the local player as player 1.
+    pub fn host(
+        mut transport: T,
+        settings: MatchSettings,
+        config: SessionConfig,
+        timeout: Duration,
+    ) -> Result<Self, NetError> {
+        let hello = Packet::Hello {
+            player: 0,
+            acknowledged: true,
+            settings,
+        }
+        .encode();
+        let start = Instant::now();
+        let mut last_hello: Option<Instant> = None;
+        loop {
+            if start.elapsed() > timeout {
+                return Err(NetError::Timeout);
+            }
+            if last_hello.is_none_or(|t| t.elapsed().as_secs_f64() > HELLO_INTERVAL) {
+                transport.send(&hello);
+                last_hello = Some(Instant::now());
+            }
+            match transport.recv().as_deref().and_then(Packet::decode) {
+                // The peer only starts sending inputs after it got the settings
+                Some(
+                    Packet::Hello {
+                        acknowledged: true, ..
+                    }
+                    | Packet::Inputs { .. },
+                ) => {
+                    return Ok(Self::new(transport, settings.new_game(), 0, config));
+                }
+                Some(Packet::Hello { .. }) => (),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    /// Asks the host for the match settings for up to `timeout`, then starts
+    /// the game with the local player as player 2.
+    pub fn join(
+        mut transport: T,
+        config: SessionConfig,
+        timeout: Duration,
+    ) -> Result<Self, NetError> {
+        let request = |settings| {
+            Packet::Hello {
+                player: 1,
+                acknowledged: false,
+                settings,
+            }
+            .encode()
+        };
+        let unknown = MatchSettings {
+            seed: 0,
+            versus: false,
+            lives: 0,
+            rounds_to_win: 0,
+            asteroid_physics: None,
+            power_ups: false,
+            ballistics: Ballistics::default(),
+        };
+        let start = Instant::now();
+        let mut last_hello: Option<Instant> = None;
+        loop {
+            if start.elapsed() > timeout {
+                return Err(NetError::Timeout);
+            }
+            if last_hello.is_none_or(|t| t.elapsed().as_secs_f64() > HELLO_INTERVAL) {
+                transport.send(&request(unknown));
+                last_hello = Some(Instant::now());
+            }
+            match transport.recv().as_deref().and_then(Packet::decode) {
+                Some(Packet::Hello {
+                    player: 0,
+                    settings,
+                    ..
+                }) => {
+                    transport.send(
+                        &Packet::Hello {
+                            player: 1,
+                            acknowledged: true,
+                            settings,
+                        }
+                        .encode(),
+                    );
+                    return Ok(Self::new(transport, settings.new_game(), 1, config));
+                }
+                Some(_) => (),
+                None => std::thread::sleep(Duration::from_millis(1)),
+            }
+        }
+    }
+
+    fn new(transport: T, game: Game, local: usize, config: SessionConfig) -> Self {
+        Self {
+            transport,
+            config,
+            local,
+            game,
+            tick: 0,
+            // Nothing is pressed during the input delay at the start
+            local_inputs: vec![0; config.input_delay as usize],
+            remote_inputs: vec![],
+            used_remote_inputs: vec![],
+            snapshots: VecDeque::new(),
+            snapshot_tick: 0,
+            confirmed: 0,
+            remote_ack: 0,
+            remote_tick: 0,
+            remote_advantage: 0,
+            last_checksum: None,
+            local_checksums: HashMap::new(),
+            remote_checksums: HashMap::new(),
+            checksums_compared: 0,
+            desyncs: vec![],
+            rollbacks: 0,
+            resimulated_ticks: 0,
+        }
+    }
+
+    /// The current, possibly predicted, game state.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Index of the local player in `game().players`.
+    pub fn local_player(&self) -> usize {
+        self.local
+    }
+
+    /// The tick the next update simulates.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Ticks before this are simulated with the real inputs of both players.
+    pub fn confirmed_tick(&self) -> u32 {
+        self.confirmed
+    }
+
+    /// Whether the peer received every local input up to `tick`.
+    pub fn peer_has_inputs_until(&self, tick: u32) -> bool {
+        self.remote_ack >= tick + self.config.input_delay
+    }
+
+    pub fn checksums_compared(&self) -> u32 {
+        self.checksums_compared
+    }
+
+    pub fn desyncs(&self) -> &[Desync] {
+        &self.desyncs
+    }
+
+    /// Number of rollbacks and the ticks simulated again because of them.
+    pub fn rollbacks(&self) -> (u32, u32) {
+        (self.rollbacks, self.resimulated_ticks)
+    }
+
+    /// Handles the received packets and sends the local inputs the peer is
+    /// missing. Called by [`Session::advance`], but also has to be called
+    /// while the game isn't advancing so the peer can catch up.
+    pub fn poll(&mut self) {
+        let received = self.remote_inputs.len();
+        while let Some(packet) = self.transport.recv() {
+            let Some(Packet::Inputs {
+                ack,
+                tick,
+                advantage,
+                checksum,
+                first,
+                inputs,
+            }) = Packet::decode(&packet)
+            else {
+                continue;
+            };
+            self.remote_ack = self.remote_ack.max(ack);
+            self.remote_tick = self.remote_tick.max(tick);
+            self.remote_advantage = advantage;
+            if let Some((tick, checksum)) = checksum {
+                self.remote_checksums.insert(tick, checksum);
+            }
+            // Later packets repeat everything that isn't acknowledged, so
+            // inputs after a gap can wait for the next one
+            let known = self.remote_inputs.len() as u32;
+            if first <= known {
+                let new = inputs.iter().skip((known - first) as usize);
+                self.remote_inputs.extend(new);
+            }
+        }
+
+        let mispredicted = (received..self.remote_inputs.len())
+            .take_while(|t| *t < self.tick as usize)
+            .find(|t| self.used_remote_inputs[*t] != self.remote_inputs[*t]);
+        if let Some(tick) = mispredicted {
+            self.rollback(tick as u32);
+        }
+        self.confirm();
+        self.send_inputs();
+    }
+
+    /// Restores the state before `tick` and simulates the ticks after it again.
+    fn rollback(&mut self, tick: u32) {
+        let index = (tick - self.snapshot_tick) as usize;
+        self.game = self.snapshots[index].clone();
+        self.snapshots.truncate(index);
+        let target = self.tick;
+        self.tick = tick;
+        while self.tick < target {
+            self.simulate();
+        }
+        self.rollbacks += 1;
+        self.resimulated_ticks += target - tick;
+    }
+
+    /// Moves the confirmed tick forward, checksumming the states it passes.
+    fn confirm(&mut self) {
+        let confirmed = (self.remote_inputs.len() as u32).min(self.tick);
+        for tick in self.confirmed..confirmed {
+            let after = tick + 1;
+            if !after.is_multiple_of(CHECKSUM_INTERVAL) {
+                continue;
+            }
+            let state = if after == self.tick {
+                Some(&self.game)
+            } else {
+                self.snapshots.get((after - self.snapshot_tick) as usize)
+            };
+            if let Some(state) = state {
+                let sum = checksum(state);
+                self.local_checksums.insert(after, sum);
+                self.last_checksum = Some((after, sum));
+            }
+        }
+        self.confirmed = self.confirmed.max(confirmed);
+
+        let ticks: Vec<u32> = self
+            .remote_checksums
+            .keys()
+            .filter(|t| self.local_checksums.contains_key(t))
+            .copied()
+            .collect();
+        for tick in ticks {
+            let local = self.local_checksums.remove(&tick).unwrap_or_default();
+            let remote = self.remote_checksums.remove(&tick).unwrap_or_default();
+            self.checksums_compared += 1;
+            if local != remote {
+                self.desyncs.push(Desync {
+                    tick,
+                    local,
+                    remote,
+                });
+            }
+        }
+        // Checksums the peer skipped never get a partner
+        let oldest = self.confirmed.saturating_sub(CHECKSUM_INTERVAL * 16);
+        self.local_checksums.retain(|t, _| *t >= oldest);
+        self.remote_checksums.retain(|t, _| *t >= oldest);
+    }
+
+    fn send_inputs(&mut self) {
+        let first = self.remote_ack.min(self.local_inputs.len() as u32);
+        let inputs: Vec<u8> = self.local_inputs[first as usize..]
+            .iter()
+            .take(u8::MAX as usize)
+            .copied()
+            .collect();
+        let packet = Packet::Inputs {
+            ack: self.remote_inputs.len() as u32,
+            tick: self.tick,
+            advantage: self.advantage().clamp(i8::MIN as i32, i8::MAX as i32) as i8,
+            checksum: self.last_checksum,
+            first,
+            inputs,
+        };
+        self.transport.send(&packet.encode());
+    }
+
+    /// Ticks the local simulation is ahead of the peer's last known tick.
+    fn advantage(&self) -> i32 {
+        self.tick as i32 - self.remote_tick as i32
+    }
+
+    /// Simulates one tick with `input` as the local input `input_delay`
+    /// ticks from now. Returns false without doing anything when the session
+    /// has to wait for the peer, either because too many remote inputs are
+    /// missing or to let a peer that is behind catch up.
+    pub fn advance(&mut self, input: &Input) -> bool {
+        self.poll();
+        let missing = self.tick.saturating_sub(self.remote_inputs.len() as u32);
+        if missing >= self.config.max_rollback {
+            return false;
+        }
+        // Both peers see each other behind by the latency, only a difference
+        // between the two views means one of them runs faster
+        if self.advantage() - self.remote_advantage as i32 >= 2 && self.tick.is_multiple_of(4) {
+            return false;
+        }
+        self.local_inputs.push(input.bits());
+        self.simulate();
+        self.send_inputs();
+        true
+    }
+
+    /// Simulates `self.tick` with the best inputs known, keeping a snapshot of the state before it.
+    fn simulate(&mut self) {
+        self.snapshots.push_back(self.game.clone());
+        while self.snapshots.len() > self.config.max_rollback as usize + 2 {
+            self.snapshots.pop_front();
+            self.snapshot_tick += 1;
+        }
+
+        let t = self.tick as usize;
+        let remote = match self.remote_inputs.get(t) {
+            Some(bits) => *bits,
+            // Predict that the remote player keeps pressing the same keys
+            None => self.remote_inputs.last().copied().unwrap_or(0),
+        };
+        if t < self.used_remote_inputs.len() {
+            self.used_remote_inputs[t] = remote;
+        } else {
+            self.used_remote_inputs.push(remote);
+        }
+
+        let local = Input::from_bits(self.local_inputs[t]);
+        let remote = Input::from_bits(remote);
+        let inputs = if self.local == 0 {
+            [local, remote]
+        } else {
+            [remote, local]
+        };
+        self.game
+            .update_players(&inputs, FIXED_DELTA_TIME, &mut DefaultRules);
+        self.tick += 1;
+    }
+}
+
+/// Seconds [`run_headless`] keeps answering the peer after the last tick.
+const LINGER_TIME: Duration = Duration::from_secs(3);
+
+/// Plays `ticks` ticks in real time without a window, with `pilot` flying the
+/// local ship or nobody, then prints how the session went.
+pub fn run_headless<T: Transport>(session: &mut Session<T>, ticks: u32, pilot: Option<&Autopilot>) {
+    let start = Instant::now();
+    while session.tick() < ticks {
+        let due = (start.elapsed().as_secs_f32() / FIXED_DELTA_TIME) as u32;
+        if session.tick() < due {
+            let input = match pilot {
+                Some(pilot) => {
+                    pilot.act_as(session.game(), session.local_player(), FIXED_DELTA_TIME)
+                }
+                None => Input::default(),
+            };
+            if session.advance(&input) {
+                continue;
+            }
+        } else {
+            session.poll();
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    // The peer may still need our inputs to confirm its last ticks
+    let finished = Instant::now();
+    while finished.elapsed() < LINGER_TIME
+        && (session.confirmed_tick() < ticks || !session.peer_has_inputs_until(ticks))
+    {
+        session.poll();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let end = Instant::now() + Duration::from_millis(200);
+    while Instant::now() < end {
+        session.poll();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let (rollbacks, resimulated) = session.rollbacks();
+    println!(
+        "Player {} simulated {} ticks, {} confirmed",
+        session.local_player() + 1,
+        session.tick(),
+        session.confirmed_tick()
+    );
+    println!("Rollbacks: {rollbacks} ({resimulated} ticks simulated again)");
+    println!(
+        "Checksums: {} compared, {} desyncs",
+        session.checksums_compared(),
+        session.desyncs().len()
+    );
+    for desync in session.desyncs() {
+        println!("  {desync}");
+    }
+    println!("Final checksum: {:016x}", checksum(session.game()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver, Sender};
+
+    /// One end of an in-memory connection.
+    struct ChannelTransport {
+        sender: Sender<Vec<u8>>,
+        receiver: Receiver<Vec<u8>>,
+    }
+
+    impl Transport for ChannelTransport {
+        fn send(&mut self, packet: &[u8]) {
+            // The peer may already be gone, like a closed UDP socket
+            let _ = self.sender.send(packet.to_vec());
+        }
+
+        fn recv(&mut self) -> Option<Vec<u8>> {
+            self.receiver.try_recv().ok()
+        }
+    }
+
+    fn connection() -> (ChannelTransport, ChannelTransport) {
+        let (host_sender, join_receiver) = channel();
+        let (join_sender, host_receiver) = channel();
+        (
+            ChannelTransport {
+                sender: host_sender,
+                receiver: host_receiver,
+            },
+            ChannelTransport {
+                sender: join_sender,
+                receiver: join_receiver,
+            },
+        )
+    }
+
+    type TestSession = Session<LossyTransport<ChannelTransport>>;
+
+    /// Connects a host and a peer over a lossy in-memory connection.
+    fn sessions(conditions: NetConditions) -> (TestSession, TestSession) {
+        let (host, join) = connection();
+        let settings = MatchSettings {
+            seed: 3,
+            versus: false,
+            lives: 3,
+            rounds_to_win: 3,
+            asteroid_physics: None,
+            power_ups: false,
+            ballistics: Ballistics::default(),
+        };
+        let timeout = Duration::from_secs(5);
+        let host = std::thread::spawn(move || {
+            let transport = LossyTransport::new(host, conditions, 1);
+            Session::host(transport, settings, SessionConfig::default(), timeout).unwrap()
+        });
+        let mut join = Session::join(
+            LossyTransport::new(join, conditions, 2),
+            SessionConfig::default(),
+            timeout,
+        )
+        .unwrap();
+        // The host starts once the joined peer's packets get through
+        while !host.is_finished() {
+            join.poll();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        (host.join().unwrap(), join)
+    }
+
+    /// Inputs that change often, so the predictions of the peer are often wrong.
+    fn input(player: usize, tick: u32) -> Input {
+        let bits = (tick / 7 + player as u32 * 5).wrapping_mul(2654435761) >> 27;
+        Input::from_bits(bits as u8 & (Input::LEFT | Input::THRUST | Input::FIRE))
+    }
+
+    /// Advances both sessions to `ticks` and waits until both confirmed every tick.
+    fn play(sessions: &mut [TestSession; 2], ticks: u32) {
+        let start = Instant::now();
+        while sessions.iter().any(|s| {
+            s.tick() < ticks || s.confirmed_tick() < ticks || !s.peer_has_inputs_until(ticks)
+        }) {
+            assert!(
+                start.elapsed() < Duration::from_secs(20),
+                "the sessions stopped making progress"
+            );
+            for session in sessions.iter_mut() {
+                if session.tick() < ticks {
+                    let input = input(session.local_player(), session.tick());
+                    session.advance(&input);
+                } else {
+                    session.poll();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn sessions_stay_in_sync_over_a_bad_connection() {
+        let conditions = NetConditions {
+            loss: 0.2,
+            latency: Duration::from_millis(10),
+            jitter: Duration::from_millis(10),
+        };
+        let (host, join) = sessions(conditions);
+        let mut sessions = [host, join];
+        play(&mut sessions, 300);
+
+        let [host, join] = &sessions;
+        assert!(host.rollbacks().0 > 0 && join.rollbacks().0 > 0);
+        assert!(host.checksums_compared() > 0 && join.checksums_compared() > 0);
+        assert!(host.desyncs().is_empty() && join.desyncs().is_empty());
+        assert_eq!(checksum(host.game()), checksum(join.game()));
+    }
+
+    #[test]
+    fn diverging_state_is_reported_as_a_desync() {
+        let (host, mut join) = sessions(NetConditions::default());
+        join.game.score += 1;
+        let mut sessions = [host, join];
+        play(&mut sessions, 2 * CHECKSUM_INTERVAL);
+
+        for session in &sessions {
+            assert!(!session.desyncs().is_empty());
+        }
+    }
+}
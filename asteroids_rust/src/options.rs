@@ -0,0 +1,425 @@
+use asteroids_rust::bot::DEFAULT_BENCH_TIME;
+use asteroids_rust::headless::HeadlessOptions;
+use asteroids_rust::net::{NetConditions, SessionConfig};
+use asteroids_rust::physics::{AsteroidPhysics, DEFAULT_RESTITUTION};
+use asteroids_rust::record::{RecordTarget, DEFAULT_RECORD_FPS};
+use asteroids_rust::weapon::Ballistics;
+use std::{path::PathBuf, time::Duration};
+
+pub const USAGE: &str = "Usage: asteroids_rust [OPTIONS]
+
+Options:
+    --seed <n>             Start a new game with this RNG seed
+    --load <file>          Start from a save file
+    --replay <file>        Play back a replay, then hand control to the player
+    --record-replay <file> Record the inputs of this session as a replay
+    --record-gif <file>    Record gameplay as an animated GIF
+    --record-frames <dir>  Record gameplay as a sequence of PNG files
+    --record-fps <n>       Frame rate of GIF and frame recordings [default: 30]
+    --script <file>        Load a Lua mod that overrides game rules
+    --shapes <file>        Load ship and asteroid shapes and colours from a JSON file
+    --coop                 Start a local two player game
+    --versus <players>     Start a local versus match for 2 to 4 players
+    --lives <n>            (Co-op, Versus) Lives of each player [default: 3]
+    --friendly-fire        (Co-op) Bullets hit the other player
+    --rounds <n>           (Versus) Round wins needed to win the match [default: 3]
+    --asteroid-physics     Let asteroids bounce off each other
+    --restitution <e>      (Asteroid physics) Bounciness from 0 to 1 [default: 0.8]
+    --power-ups            Let shot asteroids drop power-ups
+    --arcade-bullets       Bullets fly like in the arcade original, short for
+                           --bullet-range 288 --inherit-velocity --max-bullets 4
+    --bullet-range <px>    Bullets disappear after flying this far instead of
+                           after crossing the edge of the screen 5 times
+    --inherit-velocity     Bullets keep the velocity of the ship that fired them
+    --max-bullets <n>      Bullets each ship can have out at once
+    --headless <frames>    Simulate <frames> frames without opening a window,
+                           0 runs until the end of the replay
+    --png-every <n>        (Headless) Export every <n>th frame as PNG
+    --png-frames <a,b,..>  (Headless) Export the listed frames as PNG
+    --out <dir>            (Headless) Directory for exported frames [default: frames]
+    --scale <n>            (Headless) Upscale exported frames by <n> [default: 1]
+    --trace <file>         (Headless) Write a conformance trace of every update
+    --bench-bot <n>        Let the autopilot play <n> games without a window and
+                           report how it did, --seed sets the first seed
+    --bench-time <seconds> (Bench) Simulated time limit per game [default: 600]
+    --stress <asteroids>   Time the collision checks with up to <asteroids> asteroids
+                           without a window, --seed sets the seed
+    --autopilot            Start with the autopilot flying the (local) ship
+    --host <port>          Host an online two player game on this UDP port,
+                           co-op unless --versus 2 is given
+    --join <address>       Join the online game hosted at <address>, e.g. 127.0.0.1:7000
+    --input-delay <ticks>  (Online) Ticks a local input is delayed [default: 2]
+    --net-loss <fraction>  (Online) Simulate losing this fraction of sent packets
+    --net-latency <ms>     (Online) Simulate this much extra latency
+    --net-jitter <ms>      (Online) Simulate up to this much random extra latency
+    --spectate <port>      Broadcast the game to asteroids_viewer on this TCP port
+    --help                 Print this message";
+
+pub enum NetMode {
+    Host(u16),
+    Join(String),
+}
+
+/// Options of an online game.
+pub struct NetOptions {
+    pub mode: NetMode,
+    pub config: SessionConfig,
+    pub conditions: NetConditions,
+}
+
+/// Command line options.
+pub struct Options {
+    pub seed: Option<u64>,
+    pub load: Option<String>,
+    pub replay: Option<String>,
+    pub record_replay: Option<String>,
+    pub record: Option<RecordTarget>,
+    pub record_fps: u32,
+    pub script: Option<String>,
+    pub shapes: Option<String>,
+    pub coop: bool,
+    /// Number of players in a versus match.
+    pub versus: Option<usize>,
+    pub lives: Option<u32>,
+    pub friendly_fire: bool,
+    pub rounds: Option<u32>,
+    pub asteroid_physics: Option<AsteroidPhysics>,
+    pub power_ups: bool,
+    pub ballistics: Ballistics,
+    pub headless: Option<HeadlessOptions>,
+    /// Number of autopilot games to benchmark.
+    pub bench_bot: Option<u64>,
+    pub bench_time: f64,
+    /// Largest number of asteroids of the collision stress test.
+    pub stress: Option<usize>,
+    pub autopilot: bool,
+    pub net: Option<NetOptions>,
+    /// TCP port to broadcast the game on.
+    pub spectate: Option<u16>,
+    pub help: bool,
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for {flag}"))
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            load: None,
+            replay: None,
+            record_replay: None,
+            record: None,
+            record_fps: DEFAULT_RECORD_FPS,
+            script: None,
+            shapes: None,
+            coop: false,
+            versus: None,
+            lives: None,
+            friendly_fire: false,
+            rounds: None,
+            asteroid_physics: None,
+            power_ups: false,
+            ballistics: Ballistics::default(),
+            headless: None,
+            bench_bot: None,
+            bench_time: DEFAULT_BENCH_TIME,
+            stress: None,
+            autopilot: false,
+            net: None,
+            spectate: None,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut headless = HeadlessOptions::default();
+        let mut is_headless = false;
+        let mut net_mode = None;
+        let mut net_config = SessionConfig::default();
+        let mut net_conditions = NetConditions::default();
+        let mut asteroid_physics = false;
+        let mut restitution = None;
+        let mut arcade_bullets = false;
+        let mut bullet_range = None;
+        let mut inherit_velocity = false;
+        let mut max_bullets = None;
+        let millis = |flag: &str, value| parse_value(flag, value).map(Duration::from_millis);
+
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            match flag.as_str() {
+                "--seed" => options.seed = Some(parse_value(&flag, args.next())?),
+                "--load" => options.load = Some(parse_value(&flag, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&flag, args.next())?),
+                "--record-replay" => options.record_replay = Some(parse_value(&flag, args.next())?),
+                "--record-gif" => {
+                    let path = PathBuf::from(parse_value::<String>(&flag, args.next())?);
+                    options.record = Some(RecordTarget::Gif(path));
+                }
+                "--record-frames" => {
+                    let dir = PathBuf::from(parse_value::<String>(&flag, args.next())?);
+                    options.record = Some(RecordTarget::Frames(dir));
+                }
+                "--record-fps" => options.record_fps = parse_value(&flag, args.next())?,
+                "--script" => options.script = Some(parse_value(&flag, args.next())?),
+                "--shapes" => options.shapes = Some(parse_value(&flag, args.next())?),
+                "--coop" => options.coop = true,
+                "--versus" => options.versus = Some(parse_value(&flag, args.next())?),
+                "--lives" => options.lives = Some(parse_value(&flag, args.next())?),
+                "--friendly-fire" => options.friendly_fire = true,
+                "--rounds" => options.rounds = Some(parse_value(&flag, args.next())?),
+                "--asteroid-physics" => asteroid_physics = true,
+                "--restitution" => restitution = Some(parse_value(&flag, args.next())?),
+                "--power-ups" => options.power_ups = true,
+                "--arcade-bullets" => arcade_bullets = true,
+                "--bullet-range" => bullet_range = Some(parse_value(&flag, args.next())?),
+                "--inherit-velocity" => inherit_velocity = true,
+                "--max-bullets" => max_bullets = Some(parse_value(&flag, args.next())?),
+                "--headless" => {
+                    headless.frames = parse_value(&flag, args.next())?;
+                    is_headless = true;
+                }
+                "--png-every" => {
+                    let n: u64 = parse_value(&flag, args.next())?;
+                    if n == 0 {
+                        return Err("--png-every must be at least 1".to_string());
+                    }
+                    headless.png_every = Some(n);
+                }
+                "--png-frames" => {
+                    let list: String = parse_value(&flag, args.next())?;
+                    for frame in list.split(',') {
+                        headless
+                            .png_frames
+                            .push(parse_value(&flag, Some(frame.to_string()))?);
+                    }
+                }
+                "--out" => {
+                    headless.out_dir = PathBuf::from(parse_value::<String>(&flag, args.next())?)
+                }
+                "--scale" => headless.scale = parse_value(&flag, args.next())?,
+                "--trace" => {
+                    headless.trace = Some(PathBuf::from(parse_value::<String>(&flag, args.next())?))
+                }
+                "--bench-bot" => options.bench_bot = Some(parse_value(&flag, args.next())?),
+                "--bench-time" => options.bench_time = parse_value(&flag, args.next())?,
+                "--stress" => options.stress = Some(parse_value(&flag, args.next())?),
+                "--autopilot" => options.autopilot = true,
+                "--host" => net_mode = Some(NetMode::Host(parse_value(&flag, args.next())?)),
+                "--join" => net_mode = Some(NetMode::Join(parse_value(&flag, args.next())?)),
+                "--input-delay" => net_config.input_delay = parse_value(&flag, args.next())?,
+                "--net-loss" => net_conditions.loss = parse_value(&flag, args.next())?,
+                "--net-latency" => net_conditions.latency = millis(&flag, args.next())?,
+                "--net-jitter" => net_conditions.jitter = millis(&flag, args.next())?,
+                "--spectate" => options.spectate = Some(parse_value(&flag, args.next())?),
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown option '{flag}'")),
+            }
+        }
+
+        let starts = [&options.load, &options.replay]
+            .iter()
+            .filter(|s| s.is_some())
+            .count()
+            + options.seed.is_some() as usize;
+        if starts > 1 {
+            return Err("only one of --seed, --load and --replay can be used".to_string());
+        }
+        if options.bench_bot.is_some() && (options.load.is_some() || options.replay.is_some()) {
+            return Err(
+                "--bench-bot plays its own games, it can't be used with --load or --replay"
+                    .to_string(),
+            );
+        }
+        let base = if arcade_bullets {
+            Ballistics::ARCADE
+        } else {
+            Ballistics::default()
+        };
+        options.ballistics = Ballistics {
+            range: bullet_range.or(base.range),
+            inherit_velocity: inherit_velocity || base.inherit_velocity,
+            max_bullets: max_bullets.or(base.max_bullets),
+        };
+        let ballistics = options.ballistics != Ballistics::default();
+        let other_modes = options.load.is_some()
+            || options.replay.is_some()
+            || options.bench_bot.is_some()
+            || is_headless
+            || options.coop
+            || options.versus.is_some()
+            || net_mode.is_some()
+            || options.spectate.is_some()
+            || asteroid_physics
+            || options.power_ups
+            || ballistics;
+        if options.stress.is_some() && other_modes {
+            return Err(
+                "--stress runs its own games, it can't be combined with other modes".to_string(),
+            );
+        }
+        if options.stress == Some(0) {
+            return Err("--stress needs at least 1 asteroid".to_string());
+        }
+        let multiplayer = options.coop || options.versus.is_some() || net_mode.is_some();
+        if options.coop && options.versus.is_some() {
+            return Err("only one of --coop and --versus can be used".to_string());
+        }
+        if (options.coop || options.versus.is_some())
+            && (options.load.is_some() || options.replay.is_some() || options.bench_bot.is_some())
+        {
+            return Err("--coop and --versus start a new game, they can't be used with --load, --replay or --bench-bot".to_string());
+        }
+        if !multiplayer && options.lives.is_some() {
+            return Err("--lives only applies to --coop, --versus and online games".to_string());
+        }
+        if !options.coop && options.friendly_fire {
+            return Err("--friendly-fire only applies to --coop games".to_string());
+        }
+        if options.versus.is_none() && options.rounds.is_some() {
+            return Err("--rounds only applies to --versus matches".to_string());
+        }
+        if options
+            .versus
+            .is_some_and(|players| !(2..=4).contains(&players))
+        {
+            return Err("--versus needs 2 to 4 players".to_string());
+        }
+        if !asteroid_physics && restitution.is_some() {
+            return Err("--restitution only applies with --asteroid-physics".to_string());
+        }
+        if restitution.is_some_and(|e: f32| !(0. ..=1.).contains(&e)) {
+            return Err("--restitution must be between 0 and 1".to_string());
+        }
+        if asteroid_physics
+            && (options.load.is_some() || options.replay.is_some() || options.bench_bot.is_some())
+        {
+            return Err("--asteroid-physics starts a new game, it can't be used with --load, --replay or --bench-bot".to_string());
+        }
+        if options.power_ups
+            && (options.load.is_some() || options.replay.is_some() || options.bench_bot.is_some())
+        {
+            return Err("--power-ups starts a new game, it can't be used with --load, --replay or --bench-bot".to_string());
+        }
+        if bullet_range.is_some_and(|range: f32| range <= 0. || !range.is_finite()) {
+            return Err("--bullet-range must be positive".to_string());
+        }
+        if max_bullets == Some(0) {
+            return Err("--max-bullets must be at least 1".to_string());
+        }
+        if ballistics
+            && (options.load.is_some() || options.replay.is_some() || options.bench_bot.is_some())
+        {
+            return Err("--arcade-bullets, --bullet-range, --inherit-velocity and --max-bullets start a new game, they can't be used with --load, --replay or --bench-bot".to_string());
+        }
+        let shared = net_mode.is_some()
+            || options.load.is_some()
+            || options.replay.is_some()
+            || options.record_replay.is_some();
+        if options.shapes.is_some() && shared {
+            return Err("--shapes changes the hitbox and the asteroids, which replays, saves and online games don't record, it can't be used with --host, --join, --load, --replay or --record-replay".to_string());
+        }
+        let custom_rules = options.coop
+            || options.versus.is_some()
+            || asteroid_physics
+            || options.power_ups
+            || ballistics
+            || options.shapes.is_some()
+            || options.script.is_some();
+        if headless.trace.is_some() && custom_rules {
+            return Err("--trace records games with the standard rules, it can't be used with --coop, --versus, --asteroid-physics, --power-ups, --shapes, --script or the bullet options".to_string());
+        }
+        if headless.trace.is_some() && options.load.is_some() {
+            return Err("--trace starts from the seed, it can't be used with --load".to_string());
+        }
+        if asteroid_physics {
+            options.asteroid_physics = Some(AsteroidPhysics {
+                restitution: restitution.unwrap_or(DEFAULT_RESTITUTION),
+            });
+        }
+        if options.lives == Some(0) {
+            return Err("--lives must be at least 1".to_string());
+        }
+        if options.rounds == Some(0) {
+            return Err("--rounds must be at least 1".to_string());
+        }
+        if options.bench_time <= 0. {
+            return Err("--bench-time must be positive".to_string());
+        }
+        if options.record_fps == 0 {
+            return Err("--record-fps must be at least 1".to_string());
+        }
+        if options.script.is_some() && cfg!(not(feature = "scripting")) {
+            return Err("--script needs the 'scripting' feature".to_string());
+        }
+        if headless.scale == 0 {
+            return Err("--scale must be at least 1".to_string());
+        }
+        if options.spectate.is_some() && (is_headless || options.bench_bot.is_some()) {
+            return Err("--spectate broadcasts the windowed game, it can't be used with --headless or --bench-bot".to_string());
+        }
+        if !(0. ..1.).contains(&net_conditions.loss) {
+            return Err("--net-loss must be at least 0 and less than 1".to_string());
+        }
+        let net_tuned = net_config.input_delay != SessionConfig::default().input_delay
+            || net_conditions.loss > 0.
+            || !net_conditions.latency.is_zero()
+            || !net_conditions.jitter.is_zero();
+        if net_mode.is_none() && net_tuned {
+            return Err(
+                "--input-delay and the --net-* options only apply to --host and --join".to_string(),
+            );
+        }
+        if let Some(mode) = net_mode {
+            if options.load.is_some()
+                || options.replay.is_some()
+                || options.record_replay.is_some()
+                || options.script.is_some()
+                || options.bench_bot.is_some()
+                || options.coop
+            {
+                return Err("--host and --join can't be used with --load, --replay, --record-replay, --script, --bench-bot or --coop".to_string());
+            }
+            if options.versus.is_some_and(|players| players != 2) {
+                return Err("online versus matches have 2 players".to_string());
+            }
+            let host_only = options.seed.is_some()
+                || options.versus.is_some()
+                || options.lives.is_some()
+                || options.rounds.is_some()
+                || options.asteroid_physics.is_some()
+                || options.power_ups
+                || ballistics;
+            if matches!(mode, NetMode::Join(_)) && host_only {
+                return Err("the host decides --seed, --versus, --lives, --rounds, --asteroid-physics, --power-ups and how bullets fly".to_string());
+            }
+            if headless.frames == 0 && is_headless {
+                return Err("online games can't run until the end of a replay, give --headless a number of frames".to_string());
+            }
+            let exports = headless.png_every.is_some()
+                || !headless.png_frames.is_empty()
+                || headless.trace.is_some();
+            if exports || (is_headless && options.record.is_some()) {
+                return Err("online games can't export frames or traces".to_string());
+            }
+            options.net = Some(NetOptions {
+                mode,
+                config: net_config,
+                conditions: net_conditions,
+            });
+        }
+        if is_headless {
+            options.headless = Some(headless);
+        }
+
+        Ok(options)
+    }
+}
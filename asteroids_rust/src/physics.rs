@@ -0,0 +1,198 @@
+//! Collisions between asteroids, see [`Game::asteroid_physics`](crate::game::Game::asteroid_physics).
+//!
+//! Asteroids are treated as discs of radius [`Asteroid::radius`] and mass
+//! [`Asteroid::mass`]. Touching asteroids are pushed apart and exchange an
+//! impulse along the line between their centres, scaled by the restitution.
+//! Friction at the contact point trades sliding speed for spin, so glancing
+//! hits set asteroids rotating.
+
+use crate::game::Asteroid;
+use crate::grid::{Bounds, Grid};
+use crate::math::{vec2, wrap_delta};
+use crate::pool::Pool;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use serde::{Deserialize, Serialize};
+
+/// Restitution of asteroid collisions unless configured otherwise.
+pub const DEFAULT_RESTITUTION: f32 = 0.8;
+/// Friction impulse relative to the normal impulse of a collision at most.
+const FRICTION: f32 = 0.3;
+
+/// Settings of collisions between asteroids.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AsteroidPhysics {
+    /// Share of the approach speed kept by a collision, 1 is perfectly
+    /// elastic and 0 makes the asteroids stick together.
+    pub restitution: f32,
+}
+
+impl Default for AsteroidPhysics {
+    fn default() -> Self {
+        Self {
+            restitution: DEFAULT_RESTITUTION,
+        }
+    }
+}
+
+/// Bounding box of the disc of `asteroid`.
+fn disc_bounds(asteroid: &Asteroid) -> Bounds {
+    let (pos, r) = (&asteroid.transform.pos, asteroid.radius());
+    (pos.x - r, pos.x + r, pos.y - r, pos.y + r)
+}
+
+/// Separates the overlapping asteroids `a` and `b` and applies the collision impulse.
+fn collide(a: &mut Asteroid, b: &mut Asteroid, physics: &AsteroidPhysics) {
+    let offset = vec2(
+        wrap_delta(b.transform.pos.x - a.transform.pos.x, CANVAS_WIDTH as f32),
+        wrap_delta(b.transform.pos.y - a.transform.pos.y, CANVAS_HEIGHT as f32),
+    );
+    let (ra, rb) = (a.radius(), b.radius());
+    let distance = offset.length();
+    let overlap = ra + rb - distance;
+    if overlap <= 0. {
+        return;
+    }
+    // Pieces of the same asteroid start on top of each other
+    let normal = if distance > f32::EPSILON {
+        vec2(offset.x / distance, offset.y / distance)
+    } else {
+        vec2(1., 0.)
+    };
+    let tangent = vec2(-normal.y, normal.x);
+    let (inv_a, inv_b) = (1. / a.mass(), 1. / b.mass());
+    let inv_mass = inv_a + inv_b;
+
+    // Lighter asteroids are pushed further
+    let push_a = overlap * inv_a / inv_mass;
+    let push_b = overlap * inv_b / inv_mass;
+    a.transform.pos.x -= normal.x * push_a;
+    a.transform.pos.y -= normal.y * push_a;
+    b.transform.pos.x += normal.x * push_b;
+    b.transform.pos.y += normal.y * push_b;
+
+    let relative = vec2(b.velocity.x - a.velocity.x, b.velocity.y - a.velocity.y);
+    let approach = relative.dot(&normal);
+    if approach >= 0. {
+        return;
+    }
+    let impulse = -(1. + physics.restitution) * approach / inv_mass;
+
+    // Speed of b's surface past a's at the contact point
+    let sliding = relative.dot(&tangent) - ra * a.spin - rb * b.spin;
+    let (inertia_a, inertia_b) = (a.moment_of_inertia(), b.moment_of_inertia());
+    let friction = -sliding / (inv_mass + ra * ra / inertia_a + rb * rb / inertia_b);
+    let friction = friction.clamp(-FRICTION * impulse, FRICTION * impulse);
+
+    let total = vec2(
+        normal.x * impulse + tangent.x * friction,
+        normal.y * impulse + tangent.y * friction,
+    );
+    a.velocity.x -= total.x * inv_a;
+    a.velocity.y -= total.y * inv_a;
+    b.velocity.x += total.x * inv_b;
+    b.velocity.y += total.y * inv_b;
+    let contact_a = vec2(normal.x * ra, normal.y * ra);
+    let contact_b = vec2(-normal.x * rb, -normal.y * rb);
+    a.spin -= contact_a.cross(&total) / inertia_a;
+    b.spin += contact_b.cross(&total) / inertia_b;
+}
+
+/// Resolves every collision between `asteroids`, finding candidates with
+/// `grid`, which is rebuilt for that.
+pub fn collide_asteroids(
+    asteroids: &mut Pool<Asteroid>,
+    grid: &mut Grid<usize>,
+    physics: &AsteroidPhysics,
+) {
+    grid.clear();
+    for (i, asteroid) in asteroids.iter().enumerate() {
+        grid.insert(disc_bounds(asteroid), i);
+    }
+    for i in 0..asteroids.len() {
+        for cell in grid.cells(disc_bounds(&asteroids[i])) {
+            // Pairs that share several cells are checked again, but no longer overlap
+            for &j in grid.cell(cell) {
+                if j > i {
+                    let (a, b) = asteroids.pair_mut(i, j);
+                    collide(a, b, physics);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vec2;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    fn asteroid(rng: &mut Pcg32, pos: Vec2, scale: f32, velocity: Vec2) -> Asteroid {
+        let mut asteroid = Asteroid::new(rng, pos, scale, velocity);
+        asteroid.spin = 0.;
+        asteroid
+    }
+
+    fn momentum(asteroids: [&Asteroid; 2]) -> Vec2 {
+        let (x, y) = asteroids.iter().fold((0., 0.), |(x, y), a| {
+            (x + a.mass() * a.velocity.x, y + a.mass() * a.velocity.y)
+        });
+        vec2(x, y)
+    }
+
+    /// Two asteroids of scale 8 and 12 that overlap by a pixel, `b` offset from `a` by `direction`.
+    fn pair(direction: Vec2, va: Vec2, vb: Vec2) -> (Asteroid, Asteroid) {
+        let mut rng = Pcg32::seed_from_u64(7);
+        let a = asteroid(&mut rng, vec2(100., 100.), 8., va);
+        let mut b = asteroid(&mut rng, vec2(0., 0.), 12., vb);
+        let distance = a.radius() + b.radius() - 1.;
+        b.transform.pos = vec2(100. + direction.x * distance, 100. + direction.y * distance);
+        (a, b)
+    }
+
+    #[test]
+    fn head_on_collisions_keep_momentum_and_scale_the_approach_speed() {
+        for restitution in [0., 0.5, 1.] {
+            let physics = AsteroidPhysics { restitution };
+            let (mut a, mut b) = pair(vec2(1., 0.), vec2(20., 0.), vec2(-10., 0.));
+            let before = momentum([&a, &b]);
+            collide(&mut a, &mut b, &physics);
+            let after = momentum([&a, &b]);
+
+            assert!((before.x - after.x).abs() < 1e-2 && (before.y - after.y).abs() < 1e-2);
+            // Approaching at 30, separating at 30 times the restitution
+            let separating = b.velocity.x - a.velocity.x;
+            assert!(
+                (separating - 30. * restitution).abs() < 1e-3,
+                "{separating} at {restitution}"
+            );
+            // Nothing slides on a head-on hit, so nothing spins
+            assert!(a.spin.abs() < 1e-6 && b.spin.abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn glancing_collisions_keep_momentum() {
+        let physics = AsteroidPhysics::default();
+        let direction = vec2(1., 1.).normalise();
+        let (mut a, mut b) = pair(direction, vec2(30., 0.), vec2(0., -5.));
+        let before = momentum([&a, &b]);
+        collide(&mut a, &mut b, &physics);
+        let after = momentum([&a, &b]);
+
+        assert!((before.x - after.x).abs() < 1e-2 && (before.y - after.y).abs() < 1e-2);
+        assert!(a.spin != 0. && b.spin != 0.);
+    }
+
+    #[test]
+    fn separating_asteroids_are_only_pushed_apart() {
+        let physics = AsteroidPhysics::default();
+        let (mut a, mut b) = pair(vec2(1., 0.), vec2(-5., 0.), vec2(5., 0.));
+        collide(&mut a, &mut b, &physics);
+
+        assert_eq!((a.velocity.x, b.velocity.x), (-5., 5.));
+        let gap = b.transform.pos.x - a.transform.pos.x;
+        assert!(gap >= a.radius() + b.radius() - 1e-3);
+    }
+}
@@ -0,0 +1,280 @@
+//! Pre-allocated entity storage with generation checked handles.
+//!
+//! Values live contiguously in insertion order, so iterating a [`Pool`]
+//! behaves like iterating a `Vec`: [`Pool::insert`] appends,
+//! [`Pool::retain_mut`] keeps the order and [`Pool::remove`] moves the last
+//! value into the gap like `Vec::swap_remove`. A [`Handle`] keeps pointing at
+//! its value while others come and go, and stops resolving once the value is
+//! removed, even if the slot is reused.
+//!
+//! A pool serializes as the list of its values, so handles don't survive
+//! saving and loading.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::ops::Deref;
+
+/// Refers to a value in a [`Pool`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: u32,
+    generation: u32,
+}
+
+#[derive(Clone)]
+struct Slot {
+    /// Odd while the slot holds a value.
+    generation: u32,
+    /// Position of the value in `values` while occupied.
+    index: u32,
+}
+
+#[derive(Clone)]
+pub struct Pool<T> {
+    values: Vec<T>,
+    /// Slot of each value in `values`.
+    value_slots: Vec<u32>,
+    slots: Vec<Slot>,
+    /// Unoccupied slots, reused last in first out.
+    free: Vec<u32>,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl<T> Pool<T> {
+    /// A pool that holds `capacity` values before it allocates.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            values: Vec::with_capacity(capacity),
+            value_slots: Vec::with_capacity(capacity),
+            slots: Vec::with_capacity(capacity),
+            free: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Adds `value` after all others.
+    pub fn insert(&mut self, value: T) -> Handle {
+        let index = self.values.len() as u32;
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index: 0,
+                });
+                // Every slot can end up in the free list
+                self.free.reserve(self.slots.len() - self.free.len());
+                self.slots.len() as u32 - 1
+            }
+        };
+        let entry = &mut self.slots[slot as usize];
+        entry.generation = entry.generation.wrapping_add(1);
+        entry.index = index;
+        self.values.push(value);
+        self.value_slots.push(slot);
+        Handle {
+            slot,
+            generation: entry.generation,
+        }
+    }
+
+    /// Position of the value of `handle` in iteration order, `None` if it was removed.
+    pub fn index_of(&self, handle: Handle) -> Option<usize> {
+        let slot = self.slots.get(handle.slot as usize)?;
+        (slot.generation == handle.generation && slot.generation % 2 == 1)
+            .then_some(slot.index as usize)
+    }
+
+    /// Handle of the value at `index` in iteration order.
+    pub fn handle_at(&self, index: usize) -> Handle {
+        let slot = self.value_slots[index];
+        Handle {
+            slot,
+            generation: self.slots[slot as usize].generation,
+        }
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.index_of(handle).map(|i| &self.values[i])
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.index_of(handle).map(|i| &mut self.values[i])
+    }
+
+    /// Values with their handles in iteration order.
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (self.handle_at(i), v))
+    }
+
+    /// The values at the different indices `a` and `b` in iteration order.
+    pub fn pair_mut(&mut self, a: usize, b: usize) -> (&mut T, &mut T) {
+        assert_ne!(a, b, "a value can't be borrowed twice");
+        if a < b {
+            let (head, tail) = self.values.split_at_mut(b);
+            (&mut head[a], &mut tail[0])
+        } else {
+            let (head, tail) = self.values.split_at_mut(a);
+            (&mut tail[0], &mut head[b])
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.values.iter_mut()
+    }
+
+    fn free_slot(&mut self, slot: u32) {
+        let entry = &mut self.slots[slot as usize];
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(slot);
+    }
+
+    /// Removes the value of `handle`, the last value takes its place.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let index = self.index_of(handle)?;
+        self.free_slot(handle.slot);
+        let value = self.values.swap_remove(index);
+        self.value_slots.swap_remove(index);
+        if let Some(&moved) = self.value_slots.get(index) {
+            self.slots[moved as usize].index = index as u32;
+        }
+        Some(value)
+    }
+
+    /// Keeps the values `keep` returns true for, in their order.
+    pub fn retain_mut(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.values.len() {
+            let slot = self.value_slots[i];
+            if keep(&mut self.values[i]) {
+                self.values.swap(kept, i);
+                self.value_slots[kept] = slot;
+                self.slots[slot as usize].index = kept as u32;
+                kept += 1;
+            } else {
+                self.free_slot(slot);
+            }
+        }
+        self.values.truncate(kept);
+        self.value_slots.truncate(kept);
+    }
+
+    /// Like [`Pool::retain_mut`] without mutable access.
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        self.retain_mut(|value| keep(value));
+    }
+
+    /// Removes every value, keeping the memory.
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+    }
+}
+
+/// Read access to the values in iteration order.
+impl<T> Deref for Pool<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.values
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Pool<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl<T> Extend<T> for Pool<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, values: I) {
+        for value in values {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Pool<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let mut pool = Self::default();
+        pool.extend(values);
+        pool
+    }
+}
+
+impl<T: Serialize> Serialize for Pool<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.values.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Pool<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_handles_stay_stale_when_the_slot_is_reused() {
+        let mut pool = Pool::with_capacity(4);
+        let a = pool.insert('a');
+        let b = pool.insert('b');
+        assert_eq!(pool.remove(a), Some('a'));
+        assert_eq!(pool.get(a), None);
+        assert_eq!(pool.remove(a), None);
+
+        // The freed slot is reused with a new generation
+        let c = pool.insert('c');
+        assert_eq!(c.slot, a.slot);
+        assert_ne!(c, a);
+        assert_eq!(pool.get(a), None);
+        assert_eq!(pool.get(c), Some(&'c'));
+        assert_eq!(pool.get(b), Some(&'b'));
+    }
+
+    #[test]
+    fn handles_follow_their_values_when_others_are_removed() {
+        let mut pool: Pool<u32> = (0..5).collect();
+        let handles: Vec<Handle> = (0..5).map(|i| pool.handle_at(i)).collect();
+        // The last value moves into the gap
+        pool.remove(handles[1]);
+        assert_eq!(&pool[..], &[0, 4, 2, 3]);
+        assert_eq!(pool.index_of(handles[4]), Some(1));
+
+        pool.retain(|v| v % 2 == 0);
+        assert_eq!(&pool[..], &[0, 4, 2]);
+        assert_eq!(pool.get(handles[3]), None);
+        for i in [0, 2, 4] {
+            assert_eq!(pool.get(handles[i]), Some(&(i as u32)));
+        }
+        pool.clear();
+        assert!(handles.iter().all(|h| pool.get(*h).is_none()));
+    }
+
+    #[test]
+    fn pool_grows_past_its_capacity() {
+        let mut pool = Pool::with_capacity(2);
+        let handles: Vec<Handle> = (0..100).map(|i| pool.insert(i)).collect();
+        assert_eq!(pool.len(), 100);
+        for (i, handle) in handles.iter().enumerate() {
+            assert_eq!(pool.get(*handle), Some(&i));
+        }
+        // Freed slots are reused before new ones are added
+        pool.clear();
+        let handles: Vec<Handle> = (0..100).map(|i| pool.insert(i)).collect();
+        assert!(handles.iter().all(|h| h.slot < 100));
+        assert_eq!(pool.slots.len(), 100);
+    }
+}
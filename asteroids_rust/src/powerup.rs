@@ -0,0 +1,173 @@
+//! Power-ups dropped by shot asteroids, see [`Game::power_ups`](crate::game::Game::power_ups).
+//!
+//! A shot asteroid drops a power-up with a chance that grows with its
+//! [`SizeClass`]. Power-ups drift for a while and are collected by flying a
+//! ship into them. Timed power-ups last [`PowerUpKind::duration`] seconds.
+//! Collecting one that is still active adds its duration to the time left,
+//! up to [`MAX_STACKED_DURATIONS`] durations. Different power-ups combine.
+
+use crate::math::Vec2;
+use crate::shape::SizeClass;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Seconds a dropped power-up waits to be collected.
+pub const POWER_UP_LIFETIME: f64 = 10.;
+/// Distance between a ship and a power-up it collects.
+pub const PICKUP_RADIUS: f32 = 12.;
+/// Share of the speed of its asteroid a dropped power-up keeps.
+const DRIFT: f32 = 0.5;
+/// Time left of a timed power-up is capped at this many durations.
+pub const MAX_STACKED_DURATIONS: f64 = 3.;
+/// Extra lives don't go above this.
+pub const MAX_LIVES: u32 = 5;
+/// Angle between the bullets of a spread shot.
+pub const SPREAD_ANGLE: f32 = 0.25;
+/// Asteroids a piercing bullet flies through before it is used up.
+pub const PIERCE_HITS: u8 = 3;
+/// Seconds before a piercing bullet can hit again, so it doesn't also
+/// destroy the pieces of the asteroid it just hit.
+pub const PIERCE_DELAY: f64 = 0.2;
+/// Factor on the speed of the asteroids while time is slowed.
+pub const TIME_SLOW_FACTOR: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerUpKind {
+    /// Fires three bullets at once.
+    SpreadShot,
+    /// Halves the bullet cooldown.
+    RapidFire,
+    /// The ship can't crash.
+    Shield,
+    /// Bullets fly through [`PIERCE_HITS`] asteroids.
+    Piercing,
+    /// Asteroids move at [`TIME_SLOW_FACTOR`] of their speed.
+    TimeSlow,
+    /// One more life, at most [`MAX_LIVES`].
+    ExtraLife,
+}
+
+impl PowerUpKind {
+    pub const ALL: [Self; 6] = [
+        Self::SpreadShot,
+        Self::RapidFire,
+        Self::Shield,
+        Self::Piercing,
+        Self::TimeSlow,
+        Self::ExtraLife,
+    ];
+
+    /// Seconds the power-up lasts, `None` if it takes effect once.
+    pub fn duration(self) -> Option<f64> {
+        match self {
+            Self::SpreadShot | Self::RapidFire => Some(10.),
+            Self::Shield => Some(6.),
+            Self::Piercing => Some(8.),
+            Self::TimeSlow => Some(5.),
+            Self::ExtraLife => None,
+        }
+    }
+
+    /// How often the power-up drops compared to the others.
+    fn weight(self) -> u32 {
+        match self {
+            Self::SpreadShot | Self::RapidFire | Self::Piercing => 3,
+            Self::Shield | Self::TimeSlow => 2,
+            Self::ExtraLife => 1,
+        }
+    }
+
+    /// Name shown on the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::SpreadShot => "SPREAD",
+            Self::RapidFire => "RAPID",
+            Self::Shield => "SHIELD",
+            Self::Piercing => "PIERCE",
+            Self::TimeSlow => "SLOW",
+            Self::ExtraLife => "LIFE",
+        }
+    }
+
+    /// Drawn on the dropped power-up.
+    pub fn letter(self) -> &'static str {
+        match self {
+            Self::SpreadShot => "S",
+            Self::RapidFire => "R",
+            Self::Shield => "H",
+            Self::Piercing => "P",
+            Self::TimeSlow => "T",
+            Self::ExtraLife => "+",
+        }
+    }
+
+    /// Picks a kind at random by [`PowerUpKind::weight`].
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let total: u32 = Self::ALL.iter().map(|kind| kind.weight()).sum();
+        let mut roll = rng.gen_range(0..total);
+        for kind in Self::ALL {
+            if roll < kind.weight() {
+                return kind;
+            }
+            roll -= kind.weight();
+        }
+        unreachable!("the roll is below the total weight")
+    }
+}
+
+/// Chance that a shot asteroid with `scale` drops a power-up.
+pub fn drop_chance(scale: f32) -> f32 {
+    match SizeClass::of(scale) {
+        SizeClass::Small => 0.05,
+        SizeClass::Medium => 0.1,
+        SizeClass::Large => 0.15,
+    }
+}
+
+/// A power-up waiting to be collected.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+    pub pos: Vec2,
+    pub velocity: Vec2,
+    /// Game time the power-up disappears at.
+    pub expires: f64,
+}
+
+impl PowerUp {
+    /// Drops a power-up of `kind` from an asteroid at `pos` moving at `velocity`.
+    pub fn new(kind: PowerUpKind, pos: Vec2, velocity: Vec2, time: f64) -> Self {
+        Self {
+            kind,
+            pos,
+            velocity: velocity * DRIFT,
+            expires: time + POWER_UP_LIFETIME,
+        }
+    }
+}
+
+/// Timed power-ups of a player as the game time each one runs out at.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Effects {
+    until: [f64; PowerUpKind::ALL.len()],
+}
+
+impl Effects {
+    /// Seconds `kind` is still active for at `time`.
+    pub fn remaining(&self, kind: PowerUpKind, time: f64) -> f64 {
+        (self.until[kind as usize] - time).max(0.)
+    }
+
+    pub fn active(&self, kind: PowerUpKind, time: f64) -> bool {
+        self.remaining(kind, time) > 0.
+    }
+
+    /// Starts or extends the timed `kind` collected at `time`.
+    pub fn add(&mut self, kind: PowerUpKind, time: f64) {
+        let Some(duration) = kind.duration() else {
+            return;
+        };
+        let remaining = self.remaining(kind, time) + duration;
+        self.until[kind as usize] = time + remaining.min(duration * MAX_STACKED_DURATIONS);
+    }
+}
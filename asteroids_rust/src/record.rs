@@ -0,0 +1,214 @@
+//! Captures gameplay as an animated GIF or a PNG frame sequence.
+
+use crate::debug::DebugOverlay;
+use crate::game::Game;
+use crate::headless::FrameBuffer;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+pub const DEFAULT_RECORD_FPS: u32 = 30;
+
+#[derive(Debug)]
+pub enum RecordError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Io(e) => write!(f, "{e}"),
+            RecordError::Png(e) => write!(f, "{e}"),
+            RecordError::Gif(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<io::Error> for RecordError {
+    fn from(e: io::Error) -> Self {
+        RecordError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for RecordError {
+    fn from(e: png::EncodingError) -> Self {
+        RecordError::Png(e)
+    }
+}
+
+impl From<gif::EncodingError> for RecordError {
+    fn from(e: gif::EncodingError) -> Self {
+        RecordError::Gif(e)
+    }
+}
+
+pub enum RecordTarget {
+    Gif(PathBuf),
+    Frames(PathBuf),
+}
+
+/// Maps frame colors to palette indices. The game only draws a handful of
+/// distinct colors, so every color gets its own entry until the 256 entries
+/// a GIF palette allows run out, after which the nearest existing entry is used.
+#[derive(Default)]
+struct Palette {
+    colors: Vec<u32>,
+    indices: HashMap<u32, u8>,
+}
+
+impl Palette {
+    fn index(&mut self, color: u32) -> u8 {
+        if let Some(index) = self.indices.get(&color) {
+            return *index;
+        }
+
+        let index = if self.colors.len() < 256 {
+            self.colors.push(color);
+            (self.colors.len() - 1) as u8
+        } else {
+            let channels = |c: u32| [(c >> 16) as u8, (c >> 8) as u8, c as u8];
+            let distance = |other: u32| -> u32 {
+                channels(color)
+                    .iter()
+                    .zip(channels(other))
+                    .map(|(a, b)| (*a as i32 - b as i32).unsigned_abs().pow(2))
+                    .sum()
+            };
+            (0..self.colors.len())
+                .min_by_key(|i| distance(self.colors[*i]))
+                .unwrap() as u8
+        };
+        self.indices.insert(color, index);
+        index
+    }
+
+    fn to_rgb(&self) -> Vec<u8> {
+        self.colors
+            .iter()
+            .flat_map(|c| [(c >> 16) as u8, (c >> 8) as u8, *c as u8])
+            .collect()
+    }
+}
+
+enum Sink {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        palette: Palette,
+        /// Fraction of a centisecond carried over to the next frame delay.
+        delay_carry: f64,
+    },
+    Frames(PathBuf),
+}
+
+/// Samples the game at a fixed rate and writes the samples to a [`RecordTarget`].
+/// Frames are recorded at the canvas resolution.
+pub struct Recorder {
+    frame_buffer: FrameBuffer,
+    sink: Sink,
+    /// Seconds between two captured frames.
+    interval: f64,
+    time: f64,
+    next_capture: f64,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn new(target: RecordTarget, fps: u32) -> Result<Self, RecordError> {
+        let frame_buffer = FrameBuffer::new(1);
+        let sink = match target {
+            RecordTarget::Gif(path) => {
+                let (width, height) = frame_buffer.size;
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[])?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Sink::Gif {
+                    encoder,
+                    palette: Palette::default(),
+                    delay_carry: 0.,
+                }
+            }
+            RecordTarget::Frames(dir) => {
+                fs::create_dir_all(&dir)?;
+                Sink::Frames(dir)
+            }
+        };
+
+        Ok(Self {
+            frame_buffer,
+            sink,
+            interval: 1. / fps.max(1) as f64,
+            time: 0.,
+            next_capture: 0.,
+            frames: 0,
+        })
+    }
+
+    /// Advances the recording clock by `delta_time` real seconds and captures
+    /// `game` if a frame is due.
+    pub fn capture(
+        &mut self,
+        game: &Game,
+        debug_overlay: &DebugOverlay,
+        delta_time: f32,
+    ) -> Result<(), RecordError> {
+        self.time += delta_time as f64;
+        if self.time < self.next_capture {
+            return Ok(());
+        }
+        self.next_capture += self.interval;
+        // Don't try to catch up after a long stall, e.g. while the console was open
+        if self.next_capture < self.time {
+            self.next_capture = self.time + self.interval;
+        }
+
+        self.frame_buffer.render(game, debug_overlay);
+        match &mut self.sink {
+            Sink::Gif {
+                encoder,
+                palette,
+                delay_carry,
+            } => {
+                let indices: Vec<u8> = self
+                    .frame_buffer
+                    .pixels
+                    .iter()
+                    .map(|p| palette.index(*p))
+                    .collect();
+                let delay = self.interval * 100. + *delay_carry;
+                *delay_carry = delay - delay.round();
+
+                let (width, height) = self.frame_buffer.size;
+                encoder.write_frame(&gif::Frame {
+                    width: width as u16,
+                    height: height as u16,
+                    delay: delay.round() as u16,
+                    palette: Some(palette.to_rgb()),
+                    buffer: Cow::Owned(indices),
+                    ..Default::default()
+                })?;
+            }
+            Sink::Frames(dir) => {
+                let path = dir.join(format!("frame_{:06}.png", self.frames));
+                self.frame_buffer.write_png(path)?;
+            }
+        }
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    /// Writes the remaining data of the recording, returns the number of captured frames.
+    pub fn finish(self) -> Result<u64, RecordError> {
+        if let Sink::Gif { encoder, .. } = self.sink {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(self.frames)
+    }
+}
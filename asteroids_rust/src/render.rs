@@ -0,0 +1,442 @@
+use crate::debug::{
+    self, DebugOverlay, BOUNDS_COLOR, GRID_COLOR, GRID_COUNT_COLOR, VELOCITY_COLOR,
+    VELOCITY_VECTOR_SECONDS, WRAP_COUNT_COLOR,
+};
+use crate::font;
+use crate::game::{Game, Player, DANGER_ZONE, SHIELD_ENERGY};
+use crate::math::{vec2, Vec2};
+use crate::powerup::PowerUpKind;
+use crate::shape::{self, Color, Outline};
+use crate::weapon::WeaponKind;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use framebrush::{Canvas, RGBu32, WHITE, YELLOW};
+
+/// Seconds a kill stays in the kill feed.
+const KILL_FEED_SECONDS: f64 = 5.;
+/// Size of a life icon on the scoreboard in pixels.
+const LIFE_ICON_SCALE: f32 = 3.;
+/// Distance from the centre of a power-up to the corners of its diamond.
+const POWER_UP_SIZE: f32 = 6.;
+/// Seconds before a dropped power-up disappears that it blinks for.
+const POWER_UP_BLINK_SECONDS: f64 = 3.;
+const SHIELD_COLOR: RGBu32 = RGBu32::Rgb(80, 200, 255);
+/// Size of the shield energy bar in pixels.
+const ENERGY_BAR_WIDTH: usize = 30;
+const ENERGY_BAR_HEIGHT: usize = 3;
+const ENERGY_BAR_BACKGROUND: RGBu32 = RGBu32::Rgb(30, 60, 80);
+/// Length of the trail drawn behind a missile in pixels.
+const MISSILE_LENGTH: f32 = 3.;
+
+pub fn power_up_color(kind: PowerUpKind) -> RGBu32 {
+    match kind {
+        PowerUpKind::SpreadShot => RGBu32::Rgb(255, 160, 40),
+        PowerUpKind::RapidFire => RGBu32::Rgb(255, 230, 60),
+        PowerUpKind::Shield => SHIELD_COLOR,
+        PowerUpKind::Piercing => RGBu32::Rgb(255, 90, 90),
+        PowerUpKind::TimeSlow => RGBu32::Rgb(170, 130, 255),
+        PowerUpKind::ExtraLife => RGBu32::Rgb(120, 255, 140),
+    }
+}
+
+pub fn rgb([r, g, b]: Color) -> RGBu32 {
+    RGBu32::Rgb(r, g, b)
+}
+
+/// Ship colour of player `index` in the installed shapes.
+pub fn player_color(index: usize) -> RGBu32 {
+    let colors = &shape::shapes().player_colors;
+    rgb(colors[index % colors.len()])
+}
+
+/// Whether the ship of `player` is drawn, it is hidden while the player is
+/// out and blinks while it can't crash.
+fn ship_visible(game: &Game, player: &Player) -> bool {
+    let invulnerable = player.invulnerable_until - game.time;
+    player.is_active() && (invulnerable <= 0. || ((invulnerable * 10.) as u64).is_multiple_of(2))
+}
+
+/// Draws the closed outline through `points`, e.g. of a [`crate::math::Polygon`].
+pub fn draw_outline(canvas: &mut Canvas<'_, u32>, points: &[Vec2], color: &RGBu32) {
+    for (i, v) in points.iter().enumerate() {
+        let previous = &points[(i + points.len() - 1) % points.len()];
+        canvas.line(
+            v.x as i32,
+            v.y as i32,
+            previous.x as i32,
+            previous.y as i32,
+            color,
+        );
+    }
+}
+
+/// Draws the extra segments of `outline` between the matching `points`.
+fn draw_segments(canvas: &mut Canvas<'_, u32>, outline: &Outline, points: &[Vec2], color: &RGBu32) {
+    for &[a, b] in &outline.segments {
+        let (a, b) = (&points[a], &points[b]);
+        canvas.line(a.x as i32, a.y as i32, b.x as i32, b.y as i32, color);
+    }
+}
+
+/// Draws a circle around `centre` as a closed outline.
+pub fn draw_circle(canvas: &mut Canvas<'_, u32>, centre: &Vec2, radius: f32, color: &RGBu32) {
+    const SEGMENTS: usize = 16;
+    let points: [Vec2; SEGMENTS] = std::array::from_fn(|i| {
+        let (s, c) = (i as f32 / SEGMENTS as f32 * std::f32::consts::TAU).sin_cos();
+        vec2(centre.x + c * radius, centre.y + s * radius)
+    });
+    draw_outline(canvas, &points, color);
+}
+
+/// Draws the dropped power-ups as diamonds with their letter.
+fn draw_pickups(canvas: &mut Canvas<'_, u32>, game: &Game) {
+    for pickup in &game.pickups {
+        let left = pickup.expires - game.time;
+        if left < POWER_UP_BLINK_SECONDS && ((left * 5.) as u64).is_multiple_of(2) {
+            continue;
+        }
+        let Vec2 { x, y } = pickup.pos;
+        let color = power_up_color(pickup.kind);
+        let diamond = [
+            vec2(x, y - POWER_UP_SIZE),
+            vec2(x + POWER_UP_SIZE, y),
+            vec2(x, y + POWER_UP_SIZE),
+            vec2(x - POWER_UP_SIZE, y),
+        ];
+        draw_outline(canvas, &diamond, &color);
+        let (glyph_x, glyph_y) = (font::GLYPH_WIDTH / 2, font::GLYPH_HEIGHT / 2);
+        font::draw_text(
+            canvas,
+            x as i32 - glyph_x as i32,
+            y as i32 - glyph_y as i32,
+            pickup.kind.letter(),
+            &color,
+        );
+    }
+}
+
+/// The shield energy and the timed power-ups of every player with their
+/// seconds left, stacked upwards from the bottom of the player's scoreboard column.
+fn draw_status(canvas: &mut Canvas<'_, u32>, game: &Game) {
+    let column_width = CANVAS_WIDTH / game.players.len();
+    // Above the scoreboard if there is one
+    let rows = if game.players.len() > 1 { 2 } else { 1 };
+    let bottom = CANVAS_HEIGHT - rows * font::LINE_HEIGHT - 1;
+    for (i, player) in game
+        .players
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.is_active())
+    {
+        let x = i * column_width + 2;
+        let energy = player.shield.energy / SHIELD_ENERGY;
+        let bar_y = bottom + (font::LINE_HEIGHT - ENERGY_BAR_HEIGHT) / 2;
+        canvas.rect(
+            x as i32,
+            bar_y as i32,
+            ENERGY_BAR_WIDTH,
+            ENERGY_BAR_HEIGHT,
+            &ENERGY_BAR_BACKGROUND,
+        );
+        let filled = (energy * ENERGY_BAR_WIDTH as f32).round() as usize;
+        canvas.rect(
+            x as i32,
+            bar_y as i32,
+            filled,
+            ENERGY_BAR_HEIGHT,
+            &SHIELD_COLOR,
+        );
+        let weapon_x = x + ENERGY_BAR_WIDTH + 4;
+        font::draw_text(
+            canvas,
+            weapon_x as i32,
+            bottom as i32,
+            player.weapon.name(),
+            &WHITE,
+        );
+
+        let active = PowerUpKind::ALL
+            .into_iter()
+            .filter(|kind| player.effects.active(*kind, game.time));
+        for (line, kind) in active.enumerate() {
+            let seconds = player.effects.remaining(kind, game.time).ceil();
+            let text = format!("{} {seconds}", kind.name());
+            let y = (bottom - (line + 1) * font::LINE_HEIGHT) as i32;
+            font::draw_text(canvas, x as i32, y, &text, &power_up_color(kind));
+        }
+    }
+}
+
+/// Draws `game` and the enabled debug views onto `canvas`.
+pub fn draw(canvas: &mut Canvas<'_, u32>, game: &Game, debug_overlay: &DebugOverlay) {
+    canvas.fill(0);
+
+    let danger_zone_color = RGBu32::Rgb(40, 15, 0);
+    canvas.rect(0, 0, CANVAS_WIDTH, DANGER_ZONE as usize, &danger_zone_color);
+    canvas.rect(
+        0,
+        CANVAS_HEIGHT as i32 - DANGER_ZONE as i32,
+        CANVAS_WIDTH,
+        DANGER_ZONE as usize,
+        &danger_zone_color,
+    );
+    canvas.rect(
+        0,
+        0,
+        DANGER_ZONE as usize,
+        CANVAS_HEIGHT,
+        &danger_zone_color,
+    );
+    canvas.rect(
+        CANVAS_WIDTH as i32 - DANGER_ZONE as i32,
+        0,
+        DANGER_ZONE as usize,
+        CANVAS_HEIGHT,
+        &danger_zone_color,
+    );
+
+    let shapes = shape::shapes();
+    let asteroid_color = rgb(shapes.asteroids.color);
+    for asteroid in &game.asteroids {
+        draw_outline(canvas, asteroid.transform.transformed(), &asteroid_color);
+    }
+    for (i, player) in game.players.iter().enumerate() {
+        if !ship_visible(game, player) {
+            continue;
+        }
+        let color = player_color(i);
+        let points = player.ship.outline();
+        draw_outline(canvas, &points, &color);
+        draw_segments(canvas, &shapes.ship.outline, &points, &color);
+        if player.shielded(game.time) {
+            draw_circle(
+                canvas,
+                &player.ship.transform.pos,
+                player.ship.shield_radius(),
+                &SHIELD_COLOR,
+            );
+        }
+    }
+    draw_pickups(canvas, game);
+
+    let bullet_color = rgb(shapes.bullet_color);
+    for bullet in game.bullets.iter() {
+        let (x, y) = (bullet.pos.x as i32, bullet.pos.y as i32);
+        match bullet.weapon {
+            WeaponKind::Blaster | WeaponKind::Laser => canvas.put(x, y, &bullet_color),
+            WeaponKind::Missile => {
+                // A short trail behind the missile
+                let tail = vec2(bullet.dir.y, bullet.dir.x) * -MISSILE_LENGTH + &bullet.pos;
+                canvas.line(x, y, tail.x as i32, tail.y as i32, &bullet_color);
+            }
+            WeaponKind::Mine => {
+                canvas.line(x - 1, y, x + 1, y, &bullet_color);
+                canvas.line(x, y - 1, x, y + 1, &bullet_color);
+            }
+        }
+    }
+    for beam in game.beams.iter() {
+        canvas.line(
+            beam.from.x as i32,
+            beam.from.y as i32,
+            beam.to.x as i32,
+            beam.to.y as i32,
+            &bullet_color,
+        );
+    }
+
+    if game.players.len() > 1 {
+        draw_scoreboard(canvas, game);
+    }
+    draw_status(canvas, game);
+    if game.versus.is_some() {
+        draw_kill_feed(canvas, game);
+    }
+
+    if debug_overlay.hitbox {
+        for player in game.players.iter().filter(|p| p.is_active()) {
+            for &Vec2 { x: x0, y: y0 } in player.ship.hitbox.iter() {
+                for &Vec2 { x: x1, y: y1 } in player.ship.hitbox.iter() {
+                    canvas.line(x0 as i32, y0 as i32, x1 as i32, y1 as i32, &YELLOW);
+                }
+            }
+        }
+    }
+    if debug_overlay.bounds {
+        for asteroid in &game.asteroids {
+            let (left, right, top, bottom) = asteroid.bounds();
+            let (left, right, top, bottom) = (left as i32, right as i32, top as i32, bottom as i32);
+            canvas.line(left, top, right, top, &BOUNDS_COLOR);
+            canvas.line(right, top, right, bottom, &BOUNDS_COLOR);
+            canvas.line(right, bottom, left, bottom, &BOUNDS_COLOR);
+            canvas.line(left, bottom, left, top, &BOUNDS_COLOR);
+        }
+    }
+
+    if debug_overlay.grid {
+        draw_grid(canvas, game);
+    }
+
+    if debug_overlay.velocities {
+        let mut draw_velocity = |pos: &Vec2, velocity: Vec2| {
+            let end = velocity * VELOCITY_VECTOR_SECONDS + pos;
+            canvas.line(
+                pos.x as i32,
+                pos.y as i32,
+                end.x as i32,
+                end.y as i32,
+                &VELOCITY_COLOR,
+            );
+        };
+        for player in game.players.iter().filter(|p| p.is_active()) {
+            draw_velocity(&player.ship.transform.pos, player.ship.velocity.clone());
+        }
+        for asteroid in &game.asteroids {
+            draw_velocity(&asteroid.transform.pos, asteroid.velocity.clone());
+        }
+        for b in &game.bullets {
+            draw_velocity(&b.pos, b.velocity());
+        }
+    }
+
+    if debug_overlay.wrap_counts {
+        for b in &game.bullets {
+            font::draw_text(
+                canvas,
+                b.pos.x as i32 + 2,
+                b.pos.y as i32 - 6,
+                &b.wrap_count.to_string(),
+                &WRAP_COUNT_COLOR,
+            );
+        }
+    }
+
+    if debug_overlay.stats {
+        debug_overlay.draw_stats(
+            canvas,
+            2,
+            2,
+            &[
+                format!("SEED {}", game.seed),
+                format!(
+                    "ASTEROIDS {} BULLETS {}",
+                    game.asteroids.len(),
+                    game.bullets.len()
+                ),
+                format!("COOLDOWN {}MS", game.players[0].bullet_cooldown),
+                format!("ACC {:.2}", game.players[0].ship.acc),
+                format!("SCORE {} HIGH {}", game.score, game.high_score),
+                format!("WAVE {} TIMESCALE {}", game.wave, game.time_scale),
+            ],
+        );
+    }
+
+    if debug_overlay.frame_graph {
+        let x = CANVAS_WIDTH as i32 - debug::FRAME_HISTORY as i32 - 2;
+        debug_overlay.draw_frame_graph(canvas, x, 2);
+    }
+}
+
+/// Cell borders of the collision grid and how many asteroids each cell is checked against.
+fn draw_grid(canvas: &mut Canvas<'_, u32>, game: &Game) {
+    let grid = &game.grid;
+    let (columns, rows) = grid.dimensions();
+    let size = grid.cell_size();
+    for column in 1..columns {
+        let x = (column as f32 * size) as i32;
+        canvas.line(x, 0, x, CANVAS_HEIGHT as i32, &GRID_COLOR);
+    }
+    for row in 1..rows {
+        let y = (row as f32 * size) as i32;
+        canvas.line(0, y, CANVAS_WIDTH as i32, y, &GRID_COLOR);
+    }
+
+    let mut counts = vec![0; columns * rows];
+    for asteroid in &game.asteroids {
+        for cell in grid.cells(asteroid.bounds()) {
+            counts[cell] += 1;
+        }
+    }
+    for (cell, count) in counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+        let x = ((cell % columns) as f32 * size) as i32 + 2;
+        let y = ((cell / columns) as f32 * size) as i32 + 2;
+        font::draw_text(canvas, x, y, &count.to_string(), &GRID_COUNT_COLOR);
+    }
+}
+
+/// Draws the life icon of the installed shapes centred on `pos`, pointing up.
+fn draw_life_icon(canvas: &mut Canvas<'_, u32>, pos: Vec2, color: &RGBu32) {
+    let icon = &shape::shapes().life_icon;
+    // The nose is at positive y, which is down on the screen
+    let points: Vec<Vec2> = icon
+        .points()
+        .map(|v| vec2(pos.x + v.x * LIFE_ICON_SCALE, pos.y - v.y * LIFE_ICON_SCALE))
+        .collect();
+    if points.len() > 2 {
+        draw_outline(canvas, &points, color);
+    }
+    draw_segments(canvas, icon, &points, color);
+    if let [a, b] = points.as_slice() {
+        canvas.line(a.x as i32, a.y as i32, b.x as i32, b.y as i32, color);
+    }
+}
+
+/// Score and lives of every player along the bottom edge, a life icon per life.
+fn draw_scoreboard(canvas: &mut Canvas<'_, u32>, game: &Game) {
+    let column_width = CANVAS_WIDTH / game.players.len();
+    let y = (CANVAS_HEIGHT - font::LINE_HEIGHT - 1) as i32;
+    for (i, player) in game.players.iter().enumerate() {
+        let text = match (&game.versus, player.is_active()) {
+            (Some(_), true) => format!("P{} WINS {} ", i + 1, player.round_wins),
+            (Some(_), false) => format!("P{} WINS {} OUT", i + 1, player.round_wins),
+            (None, true) => format!("P{} {} ", i + 1, player.score),
+            (None, false) => format!("P{} {} OUT", i + 1, player.score),
+        };
+        let x = (i * column_width + 2) as i32;
+        let color = player_color(i);
+        font::draw_text(canvas, x, y, &text, &color);
+        if player.is_active() {
+            let spacing = 3. * LIFE_ICON_SCALE;
+            let left = (x + (text.len() * font::ADVANCE) as i32) as f32 + LIFE_ICON_SCALE;
+            let centre_y = y as f32 + font::LINE_HEIGHT as f32 / 2. - 1.;
+            for life in 0..player.lives {
+                draw_life_icon(canvas, vec2(left + life as f32 * spacing, centre_y), &color);
+            }
+        }
+    }
+}
+
+/// The round and the recent kills of a versus match in the top right corner,
+/// newest at the top.
+fn draw_kill_feed(canvas: &mut Canvas<'_, u32>, game: &Game) {
+    let Some(versus) = &game.versus else {
+        return;
+    };
+    let right = CANVAS_WIDTH as i32 - 2;
+    let text_width = |text: &str| (text.len() * font::ADVANCE) as i32;
+    let round = format!("ROUND {}", versus.round);
+    font::draw_text(canvas, right - text_width(&round), 2, &round, &WHITE);
+
+    let recent = versus
+        .kill_feed
+        .iter()
+        .rev()
+        .take_while(|kill| game.time - kill.time < KILL_FEED_SECONDS);
+    for (line, kill) in recent.enumerate() {
+        let y = 2 + ((line + 1) * font::LINE_HEIGHT) as i32;
+        let victim = format!("P{}", kill.victim + 1);
+        let (killer, killer_color) = match kill.killer {
+            Some(killer) => (format!("P{} > ", killer + 1), player_color(killer)),
+            None => ("ROCK > ".to_string(), rgb(shape::shapes().asteroids.color)),
+        };
+        let x = right - text_width(&victim);
+        font::draw_text(canvas, x, y, &victim, &player_color(kill.victim));
+        font::draw_text(canvas, x - text_width(&killer), y, &killer, &killer_color);
+    }
+}
+
+/// Renders a whole frame into `buffer`, a `size.0` x `size.1` pixel frame
+/// the canvas is scaled up to.
+pub fn render(buffer: &mut [u32], size: (usize, usize), game: &Game, debug_overlay: &DebugOverlay) {
+    let mut canvas = Canvas::new(buffer, size, (CANVAS_WIDTH, CANVAS_HEIGHT));
+    draw(&mut canvas, game, debug_overlay);
+}
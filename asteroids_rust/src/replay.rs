@@ -0,0 +1,85 @@
+//! Input recordings that reproduce a game exactly.
+//!
+//! A replay stores the state the game started from and the delta time and
+//! input of every update after it. Files use the same header + JSON layout
+//! as save files, see [`crate::save`].
+
+use crate::game::{Game, Input};
+use crate::save::{self, SaveError};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+pub const REPLAY_VERSION: u32 = 5;
+const MAGIC: &str = "ASTEROIDS-REPLAY";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tick {
+    pub delta_time: f32,
+    /// Input of the first player, see [`Input::bits`].
+    pub input: u8,
+    /// Inputs of the other players in a multiplayer game.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_inputs: Vec<u8>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub start: Game,
+    pub ticks: Vec<Tick>,
+}
+
+impl Replay {
+    pub fn new(start: &Game) -> Self {
+        Self {
+            start: start.clone(),
+            ticks: vec![],
+        }
+    }
+
+    /// Records an update with one input per player.
+    pub fn record(&mut self, inputs: &[Input], delta_time: f32) {
+        self.ticks.push(Tick {
+            delta_time,
+            input: inputs.first().map_or(0, Input::bits),
+            other_inputs: inputs.iter().skip(1).map(Input::bits).collect(),
+        });
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        fs::write(path, save::encode(MAGIC, REPLAY_VERSION, self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        save::decode(MAGIC, REPLAY_VERSION, &fs::read_to_string(path)?)
+    }
+}
+
+/// Steps through the ticks of a replay.
+pub struct ReplayPlayer {
+    replay: Replay,
+    index: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, index: 0 }
+    }
+
+    /// The state the replay starts from.
+    pub fn start(&self) -> Game {
+        self.replay.start.clone()
+    }
+
+    /// The inputs of all players and the delta time of the next update,
+    /// `None` once the replay is over.
+    pub fn next_tick(&mut self) -> Option<(Vec<Input>, f32)> {
+        let tick = self.replay.ticks.get(self.index)?;
+        self.index += 1;
+        let inputs = std::iter::once(tick.input)
+            .chain(tick.other_inputs.iter().copied())
+            .map(Input::from_bits)
+            .collect();
+        Some((inputs, tick.delta_time))
+    }
+}
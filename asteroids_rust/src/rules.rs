@@ -0,0 +1,60 @@
+//! Game rules that mods can replace, see [`Game::update_with_rules`].
+
+use crate::game::{randf32, wave_size, Asteroid, Game};
+use crate::math::vec2;
+use rand::Rng;
+use rand_pcg::Pcg32;
+use std::f32::consts::PI;
+
+/// Decisions the simulation delegates to a rule set. Every method defaults
+/// to the standard rules, so implementations only override what they change.
+pub trait Rules {
+    /// Points for shooting `asteroid`.
+    fn asteroid_score(&mut self, asteroid: &Asteroid) -> u32 {
+        let _ = asteroid;
+        1
+    }
+
+    /// Adds the pieces `asteroid` breaks into after it was shot to `pieces`.
+    /// Pieces at or below [`crate::game::MIN_ASTEROID_SCALE`] are removed on
+    /// the next update.
+    fn split_asteroid(&mut self, rng: &mut Pcg32, asteroid: &Asteroid, pieces: &mut Vec<Asteroid>) {
+        split_asteroid(rng, asteroid, pieces)
+    }
+
+    /// Asteroids that make up `wave`.
+    fn wave_asteroids(&mut self, game: &mut Game, wave: u32) -> Vec<Asteroid> {
+        wave_asteroids(game, wave)
+    }
+
+    /// Called after every update with its events in `game.events`.
+    fn after_update(&mut self, game: &mut Game) {
+        let _ = game;
+    }
+}
+
+/// The standard rules.
+pub struct DefaultRules;
+
+impl Rules for DefaultRules {}
+
+/// Breaks `asteroid` into 1 to 3 smaller pieces flying in random directions.
+pub fn split_asteroid(rng: &mut Pcg32, asteroid: &Asteroid, pieces: &mut Vec<Asteroid>) {
+    let n = rng.gen_range(1..=3);
+    pieces.extend((0..n).map(|_| {
+        let scale = asteroid.transform.scale / ((rng.gen::<f32>() * 2.) + 1.);
+        let rot = randf32(rng) * PI * 2.;
+        let velocity = vec2(randf32(rng), randf32(rng)).normalise() * 25.;
+        let mut piece = Asteroid::new(rng, asteroid.transform.pos.clone(), scale, velocity);
+        piece.transform.rot = rot;
+        piece.transform.apply();
+        piece
+    }));
+}
+
+/// [`wave_size`] random asteroids in the Danger Zone.
+pub fn wave_asteroids(game: &mut Game, wave: u32) -> Vec<Asteroid> {
+    (0..wave_size(wave))
+        .map(|_| Asteroid::random(&mut game.rng, &game.players))
+        .collect()
+}
@@ -0,0 +1,95 @@
+//! Game snapshots on disk.
+//!
+//! A save file starts with a single header line, `ASTEROIDS-SAVE <version>`,
+//! followed by the JSON encoded [`Game`]. The version is bumped whenever the
+//! layout of `Game` changes so that old files are rejected instead of being
+//! loaded into the wrong fields. Other files written by the game (e.g.
+//! replays) use the same layout with their own header.
+
+use crate::game::Game;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt, fs, io, path::Path};
+
+pub const SAVE_VERSION: u32 = 5;
+pub const QUICKSAVE_PATH: &str = "quicksave.sav";
+const MAGIC: &str = "ASTEROIDS-SAVE";
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    /// The header line is missing or malformed.
+    Header,
+    /// The file was written by a different format version.
+    Version {
+        found: u32,
+        expected: u32,
+    },
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "{e}"),
+            SaveError::Header => write!(f, "missing or unknown file header"),
+            SaveError::Version { found, expected } => {
+                write!(f, "version {found} isn't supported, expected {expected}")
+            }
+            SaveError::Json(e) => write!(f, "corrupt file: {e}"),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(e: io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(e: serde_json::Error) -> Self {
+        SaveError::Json(e)
+    }
+}
+
+/// Encodes `value` as a `<magic> <version>` header line followed by JSON.
+pub fn encode<T: Serialize>(magic: &str, version: u32, value: &T) -> Result<String, SaveError> {
+    Ok(format!(
+        "{magic} {version}\n{}",
+        serde_json::to_string(value)?
+    ))
+}
+
+/// Decodes a string written by [`encode`] with the same `magic` and `version`.
+pub fn decode<T: DeserializeOwned>(magic: &str, version: u32, s: &str) -> Result<T, SaveError> {
+    let (header, body) = s.split_once('\n').ok_or(SaveError::Header)?;
+    let found = header
+        .strip_prefix(magic)
+        .and_then(|v| v.trim().parse::<u32>().ok())
+        .ok_or(SaveError::Header)?;
+    if found != version {
+        return Err(SaveError::Version {
+            found,
+            expected: version,
+        });
+    }
+
+    Ok(serde_json::from_str(body)?)
+}
+
+pub fn to_string(game: &Game) -> Result<String, SaveError> {
+    encode(MAGIC, SAVE_VERSION, game)
+}
+
+pub fn from_str(s: &str) -> Result<Game, SaveError> {
+    decode(MAGIC, SAVE_VERSION, s)
+}
+
+pub fn save(game: &Game, path: impl AsRef<Path>) -> Result<(), SaveError> {
+    fs::write(path, to_string(game)?)?;
+    Ok(())
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<Game, SaveError> {
+    from_str(&fs::read_to_string(path)?)
+}
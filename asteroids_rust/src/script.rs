@@ -0,0 +1,583 @@
+//! Lua mods that override the game rules, see [`Rules`].
+//!
+//! A script defines any of the following global functions, the rules it
+//! doesn't define keep their standard behaviour:
+//!
+//! ```lua
+//! -- Points for shooting `asteroid`.
+//! function asteroid_score(asteroid) return 1 end
+//! -- Pieces `asteroid` breaks into, a list of {scale, vx, vy, [rot], [spin], [x], [y]}.
+//! -- Pieces keep the outline of the asteroid, returning nil keeps the standard split.
+//! function split_asteroid(asteroid, ctx) return {} end
+//! -- Asteroids of `wave`, a list of {x, y, scale, [vx], [vy]} or nil.
+//! function wave_asteroids(wave, ctx) return { ctx.random_asteroid() } end
+//! -- Called for every event, `event.kind` is "asteroid_destroyed", "life_lost",
+//! -- "ship_crashed", "power_up_collected", or in versus "round_over" and
+//! -- "match_won". Events of a single player have the player's number in
+//! -- `event.player`, a lost life also the number of the player that shot the
+//! -- ship in `event.killer` and a collected power-up its name in `event.power_up`.
+//! function on_event(event, ctx) end
+//! -- Called after every update.
+//! function on_update(ctx) end
+//! ```
+//!
+//! Asteroids are passed as `{x, y, vx, vy, scale, rot, spin}` tables. `ctx` gives
+//! access to the game:
+//!  * `ctx.random()` and `ctx.random_int(min, max)` draw from the game's RNG,
+//!    use them instead of `math.random` so replays stay reproducible.
+//!  * `wave_asteroids`, `on_event` and `on_update` also get `ctx.score`,
+//!    `ctx.wave`, `ctx.time`, `ctx.ship` (`{x, y, vx, vy, rot}` of the first
+//!    player), `ctx.players` (`{x, y, vx, vy, rot, score, lives}` for every
+//!    player), `ctx.asteroids`, `ctx.bullets` (`{x, y, owner}`),
+//!    `ctx.random_asteroid()` which returns a standard wave asteroid, and
+//!    (except `wave_asteroids`) `ctx.spawn_asteroid{x, y, scale, [vx], [vy]}`
+//!    and `ctx.add_score(n, [player])`, which credits the first player unless
+//!    told otherwise.
+//!
+//! Scripts run in a sandbox with only the `string`, `table`, `math` and `utf8`
+//! libraries and a limited instruction and memory budget. `log(...)` prints to
+//! the console. A hook that raises an error is reported and falls back to the
+//! standard rules until the script is reloaded, which happens automatically
+//! when the file changes.
+
+use crate::game::{Asteroid, Game, GameEvent, Ship};
+use crate::math::{vec2, Vec2};
+use crate::rules::{self, Rules};
+use mlua::{FromLua, FromLuaMulti, Function, HookTriggers, Lua, LuaOptions, StdLib, Table, Value};
+use rand::Rng;
+use rand_pcg::Pcg32;
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+/// Lua instructions a single hook call may run.
+const INSTRUCTION_BUDGET: u32 = 1_000_000;
+const INSTRUCTIONS_PER_HOOK: u32 = 1000;
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Lua(mlua::Error),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(e) => write!(f, "{e}"),
+            ScriptError::Lua(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<io::Error> for ScriptError {
+    fn from(e: io::Error) -> Self {
+        ScriptError::Io(e)
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(e: mlua::Error) -> Self {
+        ScriptError::Lua(e)
+    }
+}
+
+/// An asteroid described by a script.
+struct AsteroidSpec {
+    pos: Option<Vec2>,
+    velocity: Vec2,
+    scale: f32,
+    rot: Option<f32>,
+    spin: Option<f32>,
+}
+
+impl<'lua> FromLua<'lua> for AsteroidSpec {
+    fn from_lua(value: Value<'lua>, lua: &'lua Lua) -> mlua::Result<Self> {
+        let table = Table::from_lua(value, lua)?;
+        let x: Option<f32> = table.get("x")?;
+        let y: Option<f32> = table.get("y")?;
+        Ok(Self {
+            pos: x.zip(y).map(|(x, y)| vec2(x, y)),
+            velocity: vec2(
+                table.get::<_, Option<f32>>("vx")?.unwrap_or(0.),
+                table.get::<_, Option<f32>>("vy")?.unwrap_or(0.),
+            ),
+            scale: table.get("scale")?,
+            rot: table.get("rot")?,
+            spin: table.get("spin")?,
+        })
+    }
+}
+
+impl AsteroidSpec {
+    fn spawn(&self, rng: &mut Pcg32) -> Asteroid {
+        let mut asteroid = Asteroid::new(
+            rng,
+            self.pos.clone().unwrap_or(Vec2::ZERO),
+            self.scale,
+            self.velocity.clone(),
+        );
+        if let Some(rot) = self.rot {
+            asteroid.transform.rot = rot;
+            asteroid.transform.apply();
+        }
+        if let Some(spin) = self.spin {
+            asteroid.spin = spin;
+        }
+        asteroid
+    }
+}
+
+fn asteroid_table<'lua>(lua: &'lua Lua, asteroid: &Asteroid) -> mlua::Result<Table<'lua>> {
+    let t = &asteroid.transform;
+    let table = lua.create_table()?;
+    table.set("x", t.pos.x)?;
+    table.set("y", t.pos.y)?;
+    table.set("vx", asteroid.velocity.x)?;
+    table.set("vy", asteroid.velocity.y)?;
+    table.set("scale", t.scale)?;
+    table.set("rot", t.rot)?;
+    table.set("spin", asteroid.spin)?;
+    Ok(table)
+}
+
+fn ship_table<'lua>(lua: &'lua Lua, ship: &Ship) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("x", ship.transform.pos.x)?;
+    table.set("y", ship.transform.pos.y)?;
+    table.set("vx", ship.velocity.x)?;
+    table.set("vy", ship.velocity.y)?;
+    table.set("rot", ship.transform.rot)?;
+    Ok(table)
+}
+
+fn event_table<'lua>(lua: &'lua Lua, event: &GameEvent) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    match *event {
+        GameEvent::AsteroidDestroyed { player, score } => {
+            table.set("kind", "asteroid_destroyed")?;
+            table.set("player", player + 1)?;
+            table.set("score", score)?;
+        }
+        GameEvent::LifeLost {
+            player,
+            lives,
+            killer,
+        } => {
+            table.set("kind", "life_lost")?;
+            table.set("player", player + 1)?;
+            table.set("lives", lives)?;
+            table.set("killer", killer.map(|k| k + 1))?;
+        }
+        GameEvent::ShipCrashed { score, high_score } => {
+            table.set("kind", "ship_crashed")?;
+            table.set("score", score)?;
+            table.set("high_score", high_score)?;
+        }
+        GameEvent::RoundOver { round, winner } => {
+            table.set("kind", "round_over")?;
+            table.set("round", round)?;
+            table.set("winner", winner.map(|w| w + 1))?;
+        }
+        GameEvent::MatchWon { player } => {
+            table.set("kind", "match_won")?;
+            table.set("player", player + 1)?;
+        }
+        GameEvent::PowerUpCollected { player, kind } => {
+            table.set("kind", "power_up_collected")?;
+            table.set("player", player + 1)?;
+            table.set("power_up", kind.name().to_ascii_lowercase())?;
+        }
+    }
+    Ok(table)
+}
+
+/// Creates a sandboxed Lua state that runs the script in `source`.
+fn create_lua(
+    source: &str,
+    name: &str,
+    log: Rc<RefCell<Vec<String>>>,
+    instructions: Rc<Cell<u32>>,
+) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::STRING | StdLib::TABLE | StdLib::MATH | StdLib::UTF8,
+        LuaOptions::default(),
+    )?;
+    lua.set_memory_limit(MEMORY_LIMIT)?;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(INSTRUCTIONS_PER_HOOK),
+        move |_, _| {
+            instructions.set(instructions.get() + INSTRUCTIONS_PER_HOOK);
+            if instructions.get() > INSTRUCTION_BUDGET {
+                return Err(mlua::Error::RuntimeError(
+                    "instruction budget exceeded, is there an endless loop?".to_string(),
+                ));
+            }
+            Ok(())
+        },
+    );
+
+    {
+        let globals = lua.globals();
+        // Unseeded randomness would break replays, scripts use `ctx.random` instead
+        let math: Table = globals.get("math")?;
+        math.set("random", Value::Nil)?;
+        math.set("randomseed", Value::Nil)?;
+        globals.set(
+            "log",
+            lua.create_function(move |lua, values: mlua::Variadic<Value>| {
+                let line = values
+                    .into_iter()
+                    .map(|v| {
+                        lua.coerce_string(v)
+                            .ok()
+                            .flatten()
+                            .map(|s| s.to_string_lossy().into_owned())
+                    })
+                    .map(|s| s.unwrap_or_else(|| "?".to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                log.borrow_mut().push(line);
+                Ok(())
+            })?,
+        )?;
+        lua.load(source).set_name(name).exec()?;
+    }
+    Ok(lua)
+}
+
+/// Rules implemented by a Lua script.
+pub struct ScriptRules {
+    lua: Lua,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    /// Hooks that raised an error since the script was loaded.
+    failed: HashSet<&'static str>,
+    /// Lines for the console, errors start with "ERROR: ".
+    messages: Rc<RefCell<Vec<String>>>,
+    /// Instructions run by the current hook call.
+    instructions: Rc<Cell<u32>>,
+}
+
+impl ScriptRules {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScriptError> {
+        let path = path.as_ref().to_path_buf();
+        let messages = Rc::new(RefCell::new(vec![]));
+        let instructions = Rc::new(Cell::new(0));
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let lua = create_lua(
+            &fs::read_to_string(&path)?,
+            &path.display().to_string(),
+            messages.clone(),
+            instructions.clone(),
+        )?;
+        Ok(Self {
+            lua,
+            path,
+            modified,
+            failed: HashSet::new(),
+            messages,
+            instructions,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reloads the script if the file changed since it was loaded. If the new
+    /// version fails to load the error is reported and the old one stays active.
+    /// Returns whether the script was reloaded.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+
+        let reloaded = fs::read_to_string(&self.path)
+            .map_err(ScriptError::from)
+            .and_then(|source| {
+                let name = self.path.display().to_string();
+                Ok(create_lua(
+                    &source,
+                    &name,
+                    self.messages.clone(),
+                    self.instructions.clone(),
+                )?)
+            });
+        match reloaded {
+            Ok(lua) => {
+                self.lua = lua;
+                self.failed.clear();
+                self.messages
+                    .borrow_mut()
+                    .push(format!("reloaded {}", self.path.display()));
+                true
+            }
+            Err(e) => {
+                self.report(format!("couldn't reload {}: {e}", self.path.display()));
+                false
+            }
+        }
+    }
+
+    /// Takes the lines logged by the script and the errors it raised since the last call.
+    pub fn take_messages(&mut self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.borrow_mut())
+    }
+
+    fn report(&self, error: String) {
+        // Lua errors end with a traceback that doesn't fit the console
+        let error = error.lines().next().unwrap_or_default();
+        self.messages.borrow_mut().push(format!("ERROR: {error}"));
+    }
+
+    /// Reports `error` and stops calling the hook `name` until the script is reloaded.
+    fn disable(&mut self, name: &'static str, error: mlua::Error) {
+        self.report(format!(
+            "{name} is disabled until the script is reloaded: {error}"
+        ));
+        self.failed.insert(name);
+    }
+
+    /// The global function `name` unless it's undefined or failed before.
+    fn hook(&self, name: &'static str) -> Option<Function<'_>> {
+        if self.failed.contains(name) {
+            return None;
+        }
+        self.lua
+            .globals()
+            .get::<_, Option<Function>>(name)
+            .ok()
+            .flatten()
+    }
+
+    /// Handles the outcome of calling the hook `name`, returning the value on success.
+    fn finish<T>(&mut self, name: &'static str, result: mlua::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.disable(name, e);
+                None
+            }
+        }
+    }
+
+    /// Adds the random number functions backed by `rng` to `ctx`.
+    fn add_random<'lua, 'scope, 'rng: 'scope>(
+        scope: &mlua::Scope<'lua, 'scope>,
+        ctx: &Table<'lua>,
+        rng: &'scope RefCell<&'rng mut Pcg32>,
+    ) -> mlua::Result<()> {
+        ctx.set(
+            "random",
+            scope.create_function(|_, ()| Ok(rng.borrow_mut().gen::<f32>()))?,
+        )?;
+        ctx.set(
+            "random_int",
+            scope.create_function(|_, (min, max): (i64, i64)| {
+                if min > max {
+                    return Err(mlua::Error::RuntimeError(format!(
+                        "empty range {min}..{max}"
+                    )));
+                }
+                Ok(rng.borrow_mut().gen_range(min..=max))
+            })?,
+        )?;
+        Ok(())
+    }
+
+    /// Calls `function` with `arg` (if any) and a context for `game`.
+    /// `can_spawn` adds the functions that change the game.
+    fn call_with_game<'lua, T: FromLuaMulti<'lua>>(
+        lua: &'lua Lua,
+        function: &Function<'lua>,
+        game: &mut Game,
+        arg: Option<Value<'lua>>,
+        can_spawn: bool,
+    ) -> mlua::Result<T> {
+        let ctx = lua.create_table()?;
+        ctx.set("score", game.score)?;
+        ctx.set("wave", game.wave)?;
+        ctx.set("time", game.time)?;
+        ctx.set("ship", ship_table(lua, &game.players[0].ship)?)?;
+        let players = lua.create_table()?;
+        for (i, p) in game.players.iter().enumerate() {
+            let player = ship_table(lua, &p.ship)?;
+            player.set("score", p.score)?;
+            player.set("lives", p.lives)?;
+            players.set(i + 1, player)?;
+        }
+        ctx.set("players", players)?;
+        ctx.set(
+            "asteroids",
+            lua.create_sequence_from(
+                game.asteroids
+                    .iter()
+                    .map(|a| asteroid_table(lua, a))
+                    .collect::<mlua::Result<Vec<_>>>()?,
+            )?,
+        )?;
+        let bullets = lua.create_table()?;
+        for (i, b) in game.bullets.iter().enumerate() {
+            let bullet = lua.create_table()?;
+            bullet.set("x", b.pos.x)?;
+            bullet.set("y", b.pos.y)?;
+            bullet.set("owner", b.owner + 1)?;
+            bullets.set(i + 1, bullet)?;
+        }
+        ctx.set("bullets", bullets)?;
+
+        let Game {
+            rng,
+            players,
+            asteroids,
+            score,
+            ..
+        } = game;
+        let player_count = players.len();
+        let rng = RefCell::new(rng);
+        let spawned = RefCell::new(vec![]);
+        let added_score = RefCell::new(vec![0u32; player_count]);
+        let result = lua.scope(|scope| {
+            Self::add_random(scope, &ctx, &rng)?;
+            ctx.set(
+                "random_asteroid",
+                scope.create_function(|lua, ()| {
+                    asteroid_table(lua, &Asteroid::random(&mut **rng.borrow_mut(), players))
+                })?,
+            )?;
+            if can_spawn {
+                ctx.set(
+                    "spawn_asteroid",
+                    scope.create_function(|_, spec: AsteroidSpec| {
+                        spawned.borrow_mut().push(spec.spawn(&mut rng.borrow_mut()));
+                        Ok(())
+                    })?,
+                )?;
+                ctx.set(
+                    "add_score",
+                    scope.create_function(|_, (n, player): (u32, Option<usize>)| {
+                        let player = player.unwrap_or(1);
+                        if !(1..=player_count).contains(&player) {
+                            return Err(mlua::Error::RuntimeError(format!(
+                                "there is no player {player}"
+                            )));
+                        }
+                        let added = &mut added_score.borrow_mut()[player - 1];
+                        *added = added.saturating_add(n);
+                        Ok(())
+                    })?,
+                )?;
+            }
+            match arg {
+                Some(arg) => function.call((arg, ctx.clone())),
+                None => function.call(ctx.clone()),
+            }
+        });
+        asteroids.extend(spawned.into_inner());
+        for (player, added) in players.iter_mut().zip(added_score.into_inner()) {
+            player.score = player.score.saturating_add(added);
+            *score = score.saturating_add(added);
+        }
+        result
+    }
+}
+
+impl Rules for ScriptRules {
+    fn asteroid_score(&mut self, asteroid: &Asteroid) -> u32 {
+        let Some(function) = self.hook("asteroid_score") else {
+            return rules::DefaultRules.asteroid_score(asteroid);
+        };
+        self.instructions.set(0);
+        let result = asteroid_table(&self.lua, asteroid).and_then(|a| function.call::<_, u32>(a));
+        drop(function);
+        self.finish("asteroid_score", result)
+            .unwrap_or_else(|| rules::DefaultRules.asteroid_score(asteroid))
+    }
+
+    fn split_asteroid(&mut self, rng: &mut Pcg32, asteroid: &Asteroid, pieces: &mut Vec<Asteroid>) {
+        let Some(function) = self.hook("split_asteroid") else {
+            return rules::split_asteroid(rng, asteroid, pieces);
+        };
+        self.instructions.set(0);
+        let lua = &self.lua;
+        let rng_cell = RefCell::new(&mut *rng);
+        let result = lua.scope(|scope| {
+            let ctx = lua.create_table()?;
+            Self::add_random(scope, &ctx, &rng_cell)?;
+            function.call::<_, Option<Vec<AsteroidSpec>>>((asteroid_table(lua, asteroid)?, ctx))
+        });
+        drop(function);
+
+        match self.finish("split_asteroid", result).flatten() {
+            Some(specs) => pieces.extend(specs.iter().map(|piece| {
+                let mut transform = asteroid.transform.clone();
+                transform.scale = piece.scale;
+                transform.pos = piece.pos.clone().unwrap_or(transform.pos);
+                transform.rot = piece.rot.unwrap_or(transform.rot);
+                transform.apply();
+                Asteroid {
+                    transform,
+                    velocity: piece.velocity.clone(),
+                    spin: piece.spin.unwrap_or(asteroid.spin),
+                }
+            })),
+            None => rules::split_asteroid(rng, asteroid, pieces),
+        }
+    }
+
+    fn wave_asteroids(&mut self, game: &mut Game, wave: u32) -> Vec<Asteroid> {
+        let Some(function) = self.hook("wave_asteroids") else {
+            return rules::wave_asteroids(game, wave);
+        };
+        self.instructions.set(0);
+        let result = Self::call_with_game::<Option<Vec<AsteroidSpec>>>(
+            &self.lua,
+            &function,
+            game,
+            Some(Value::Integer(wave as i64)),
+            false,
+        );
+        drop(function);
+
+        match self.finish("wave_asteroids", result).flatten() {
+            Some(specs) => specs.iter().map(|spec| spec.spawn(&mut game.rng)).collect(),
+            None => rules::wave_asteroids(game, wave),
+        }
+    }
+
+    fn after_update(&mut self, game: &mut Game) {
+        let result = self.hook("on_event").map(|function| {
+            for event in game.events.clone() {
+                self.instructions.set(0);
+                let event = event_table(&self.lua, &event)?;
+                Self::call_with_game::<()>(
+                    &self.lua,
+                    &function,
+                    game,
+                    Some(Value::Table(event)),
+                    true,
+                )?;
+            }
+            Ok(())
+        });
+        if let Some(result) = result {
+            self.finish("on_event", result);
+        }
+
+        let result = self.hook("on_update").map(|function| {
+            self.instructions.set(0);
+            Self::call_with_game::<()>(&self.lua, &function, game, None, true)
+        });
+        if let Some(result) = result {
+            self.finish("on_update", result);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Procedurally generated asteroid outlines and the shape file.
+//!
+//! Every asteroid outline is made from one of a few [`ShapeTemplate`]s of the
+//! asteroid's [`SizeClass`]. The vertices go around the centre at strictly
+//! increasing angles, so the outline never crosses itself and the centre is
+//! always inside it.
+//!
+//! A shape file (see [`ShapeSet`]) restyles the game: the ship's outline and
+//! hitbox, the life icon, the asteroid templates and the colours. It is
+//! loaded once at startup with [`install`]. The hitbox and the templates
+//! change the simulation, so a game with a shape file can't be replayed,
+//! saved or played online.
+
+use crate::math::{vec2, Polygon, Vec2, INLINE_VERTICES};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::{f32::consts::TAU, fmt, fs, io, path::Path, sync::OnceLock};
+
+/// Vertex counts every template stays within.
+pub const MIN_ASTEROID_VERTICES: usize = 8;
+pub const MAX_ASTEROID_VERTICES: usize = 14;
+
+/// Parameters of a family of asteroid outlines.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShapeTemplate {
+    pub min_vertices: usize,
+    pub max_vertices: usize,
+    /// Deepest dent as a share of the radius, 0 makes a regular polygon.
+    pub jaggedness: f32,
+    /// Share of its even angular spacing a vertex may move by, below 1.
+    pub angle_jitter: f32,
+}
+
+/// Asteroids are grouped by size so small rocks get simpler outlines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SizeClass {
+    Small,
+    Medium,
+    Large,
+}
+
+impl SizeClass {
+    /// Class of an asteroid with `scale`.
+    pub fn of(scale: f32) -> Self {
+        if scale < 6. {
+            Self::Small
+        } else if scale < 11. {
+            Self::Medium
+        } else {
+            Self::Large
+        }
+    }
+
+    /// Templates of the class in the installed [`shapes`].
+    pub fn templates(self) -> &'static [ShapeTemplate] {
+        let asteroids = &shapes().asteroids;
+        match self {
+            Self::Small => &asteroids.small,
+            Self::Medium => &asteroids.medium,
+            Self::Large => &asteroids.large,
+        }
+    }
+}
+
+const fn template(
+    min_vertices: usize,
+    max_vertices: usize,
+    jaggedness: f32,
+    angle_jitter: f32,
+) -> ShapeTemplate {
+    ShapeTemplate {
+        min_vertices,
+        max_vertices,
+        jaggedness,
+        angle_jitter,
+    }
+}
+
+// Round pebbles and a chipped one
+const SMALL: [ShapeTemplate; 2] = [template(8, 9, 0.2, 0.3), template(8, 10, 0.35, 0.5)];
+// Lumpy, elongated by uneven spacing, and cratered
+const MEDIUM: [ShapeTemplate; 3] = [
+    template(9, 11, 0.25, 0.4),
+    template(10, 12, 0.2, 0.8),
+    template(9, 12, 0.45, 0.3),
+];
+// Boulders, broken slabs and spiky rocks
+const LARGE: [ShapeTemplate; 3] = [
+    template(11, 13, 0.25, 0.4),
+    template(12, 14, 0.35, 0.7),
+    template(12, 14, 0.5, 0.2),
+];
+
+/// Random outlines [`generate`] tries before it falls back to a regular polygon.
+const GENERATE_ATTEMPTS: usize = 4;
+
+/// A random outline made from `template` at the origin with scale 1. No
+/// vertex is farther than 1 from the centre.
+pub fn generate(rng: &mut impl Rng, template: &ShapeTemplate) -> Polygon {
+    for _ in 0..GENERATE_ATTEMPTS {
+        let vertices = random_vertices(rng, template);
+        if is_simple(&vertices) {
+            return Polygon::new(Vec2::ZERO, &vertices, 1., 0.);
+        }
+    }
+    // Rounding could in theory fold a very jagged outline, without jaggedness
+    // and jitter the vertices form a regular polygon
+    let regular = ShapeTemplate {
+        jaggedness: 0.,
+        angle_jitter: 0.,
+        ..*template
+    };
+    Polygon::new(Vec2::ZERO, &random_vertices(rng, &regular), 1., 0.)
+}
+
+fn random_vertices(
+    rng: &mut impl Rng,
+    template: &ShapeTemplate,
+) -> SmallVec<[Vec2; INLINE_VERTICES]> {
+    let max_vertices = template.max_vertices.max(3);
+    let n = rng.gen_range(template.min_vertices.clamp(3, max_vertices)..=max_vertices);
+    let sector = TAU / n as f32;
+    let jitter = template.angle_jitter.clamp(0., 0.99);
+    (0..n)
+        .map(|i| {
+            // Each vertex stays inside its own sector, keeping the angles in order
+            let angle = (i as f32 + 0.5 + jitter * (rng.gen::<f32>() - 0.5)) * sector;
+            let radius = 1. - template.jaggedness * rng.gen::<f32>();
+            vec2(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+/// Random outline from one of the templates of the size class of `scale`,
+/// see [`generate`].
+pub fn asteroid_outline(rng: &mut impl Rng, scale: f32) -> Polygon {
+    let templates = SizeClass::of(scale).templates();
+    let template = &templates[rng.gen_range(0..templates.len())];
+    generate(rng, template)
+}
+
+fn segments_intersect(a: &Vec2, b: &Vec2, c: &Vec2, d: &Vec2) -> bool {
+    let side = |p: &Vec2, q: &Vec2, r: &Vec2| {
+        vec2(q.x - p.x, q.y - p.y).cross(&vec2(r.x - p.x, r.y - p.y))
+    };
+    let (d1, d2) = (side(c, d, a), side(c, d, b));
+    let (d3, d4) = (side(a, b, c), side(a, b, d));
+    d1 * d2 < 0. && d3 * d4 < 0.
+}
+
+/// Whether the closed outline through `vertices` doesn't cross itself.
+/// Edges that only touch at a point don't count as crossing.
+pub fn is_simple(vertices: &[Vec2]) -> bool {
+    let n = vertices.len();
+    let edge = |i: usize| (&vertices[i], &vertices[(i + 1) % n]);
+    (0..n).all(|i| {
+        // Neighbouring edges share a vertex
+        (i + 2..n).filter(|&j| (j + 1) % n != i).all(|j| {
+            let ((a, b), (c, d)) = (edge(i), edge(j));
+            !segments_intersect(a, b, c, d)
+        })
+    })
+}
+
+/// RGB colour in a shape file.
+pub type Color = [u8; 3];
+
+/// Lines through points at scale 1 around the centre.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Outline {
+    /// Corners of the closed outline.
+    pub vertices: Vec<[f32; 2]>,
+    /// Extra lines between two vertices given by their indices, e.g. details
+    /// inside the outline.
+    #[serde(default)]
+    pub segments: Vec<[usize; 2]>,
+}
+
+impl Outline {
+    pub fn points(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.vertices.iter().map(|&[x, y]| vec2(x, y))
+    }
+
+    fn validate(&self, name: &str, min_vertices: usize) -> Result<(), String> {
+        if self.vertices.len() < min_vertices {
+            return Err(format!("{name} needs at least {min_vertices} vertices"));
+        }
+        if self.vertices.iter().flatten().any(|c| !c.is_finite()) {
+            return Err(format!("{name} has a vertex that isn't a finite number"));
+        }
+        if let Some([a, b]) = self
+            .segments
+            .iter()
+            .find(|s| s.iter().any(|&i| i >= self.vertices.len()))
+        {
+            return Err(format!(
+                "segment [{a}, {b}] of {name} refers to a missing vertex"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The ship, drawn in the colour of its player.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ShipShape {
+    #[serde(flatten)]
+    pub outline: Outline,
+    /// Corners of the box bullets and asteroids hit, at scale 1 like the outline.
+    pub hitbox: [[f32; 2]; 4],
+}
+
+/// Outline templates per [`SizeClass`] and the colour of the asteroids.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AsteroidShapes {
+    pub color: Color,
+    pub small: Vec<ShapeTemplate>,
+    pub medium: Vec<ShapeTemplate>,
+    pub large: Vec<ShapeTemplate>,
+}
+
+/// Everything a shape file describes, see [`ShapeSet::load`]. Sections a
+/// file leaves out keep their standard look.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShapeSet {
+    pub ship: ShipShape,
+    /// Drawn once per remaining life on the scoreboard.
+    pub life_icon: Outline,
+    pub asteroids: AsteroidShapes,
+    pub bullet_color: Color,
+    /// Ship colour of each player, repeating when there are more players.
+    pub player_colors: Vec<Color>,
+}
+
+impl Default for ShapeSet {
+    fn default() -> Self {
+        let corner = vec2(1., 1.).normalise();
+        let (x, y) = (corner.x, corner.y);
+        let ship = Outline {
+            vertices: vec![[-x, -y], [x, -y], [0., 1.]],
+            segments: vec![],
+        };
+        Self {
+            ship: ShipShape {
+                outline: ship.clone(),
+                hitbox: [
+                    [-x / 2., 0.5],
+                    [x / 2., 0.5],
+                    [-x / 2., -y / 2.],
+                    [x / 2., -y / 2.],
+                ],
+            },
+            life_icon: ship,
+            asteroids: AsteroidShapes {
+                color: [0, 255, 0],
+                small: SMALL.to_vec(),
+                medium: MEDIUM.to_vec(),
+                large: LARGE.to_vec(),
+            },
+            bullet_color: [86, 182, 194],
+            player_colors: vec![[255, 0, 0], [70, 140, 255], [255, 200, 40], [230, 90, 230]],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ShapeError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The file parsed but describes something unusable.
+    Invalid(String),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeError::Io(e) => write!(f, "{e}"),
+            ShapeError::Json(e) => write!(f, "corrupt file: {e}"),
+            ShapeError::Invalid(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl ShapeSet {
+    /// Reads a JSON shape file, see `shapes/default.json`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ShapeError> {
+        let json = fs::read_to_string(path).map_err(ShapeError::Io)?;
+        let set: Self = serde_json::from_str(&json).map_err(ShapeError::Json)?;
+        set.validate().map_err(ShapeError::Invalid)?;
+        Ok(set)
+    }
+
+    /// Checks everything [`ShapeSet::load`] can't express in the format.
+    pub fn validate(&self) -> Result<(), String> {
+        self.ship.outline.validate("ship", 3)?;
+        if self.ship.hitbox.iter().flatten().any(|c| !c.is_finite()) {
+            return Err("the ship hitbox has a corner that isn't a finite number".to_string());
+        }
+        self.life_icon.validate("life_icon", 2)?;
+        let classes = [
+            ("small", &self.asteroids.small),
+            ("medium", &self.asteroids.medium),
+            ("large", &self.asteroids.large),
+        ];
+        for (class, templates) in classes {
+            if templates.is_empty() {
+                return Err(format!("there are no {class} asteroid templates"));
+            }
+            for (i, t) in templates.iter().enumerate() {
+                if t.min_vertices < 3 || t.min_vertices > t.max_vertices {
+                    return Err(format!(
+                        "{class} asteroid template {i} needs 3 <= min_vertices <= max_vertices"
+                    ));
+                }
+                if !(0. ..1.).contains(&t.jaggedness) || !(0. ..1.).contains(&t.angle_jitter) {
+                    return Err(format!("{class} asteroid template {i} needs jaggedness and angle_jitter from 0 to below 1"));
+                }
+            }
+        }
+        if self.player_colors.is_empty() {
+            return Err("player_colors needs at least one colour".to_string());
+        }
+        Ok(())
+    }
+}
+
+static SHAPES: OnceLock<ShapeSet> = OnceLock::new();
+
+/// Replaces the standard shapes for the rest of the program. Only works
+/// before the shapes were first used, returns whether it did.
+pub fn install(shapes: ShapeSet) -> bool {
+    SHAPES.set(shapes).is_ok()
+}
+
+/// The installed shapes, the standard ones unless [`install`] was called.
+pub fn shapes() -> &'static ShapeSet {
+    SHAPES.get_or_init(ShapeSet::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_pcg::Pcg32;
+
+    const SEEDS: u64 = 2000;
+
+    fn assert_valid(outline: &Polygon) {
+        let vertices = outline.vertices();
+        assert!(is_simple(vertices));
+        assert!((MIN_ASTEROID_VERTICES..=MAX_ASTEROID_VERTICES).contains(&vertices.len()));
+        assert!(vertices.iter().all(|v| v.length() <= 1. + 1e-6));
+    }
+
+    #[test]
+    fn every_template_generates_simple_outlines() {
+        for template in SMALL.iter().chain(&MEDIUM).chain(&LARGE) {
+            for seed in 0..SEEDS {
+                assert_valid(&generate(&mut Pcg32::seed_from_u64(seed), template));
+            }
+        }
+    }
+
+    #[test]
+    fn every_size_class_generates_simple_outlines() {
+        for scale in [4., 8., 12., 16.] {
+            for seed in 0..SEEDS {
+                assert_valid(&asteroid_outline(&mut Pcg32::seed_from_u64(seed), scale));
+            }
+        }
+    }
+
+    #[test]
+    fn crossing_outlines_are_not_simple() {
+        let square = [vec2(0., 0.), vec2(1., 0.), vec2(1., 1.), vec2(0., 1.)];
+        let bow_tie = [vec2(0., 0.), vec2(1., 1.), vec2(1., 0.), vec2(0., 1.)];
+        assert!(is_simple(&square));
+        assert!(!is_simple(&bow_tie));
+    }
+}
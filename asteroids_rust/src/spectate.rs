@@ -0,0 +1,263 @@
+//! Live broadcasts of a running game to viewers over TCP.
+//!
+//! A stream starts with a single header line, `ASTEROIDS-STREAM <version>`,
+//! followed by one JSON encoded [`Message`] per line. Viewers get a snapshot
+//! of the whole game when they connect and then the inputs of every update,
+//! which they simulate themselves. Every [`Broadcaster::snapshot_interval`]
+//! game seconds the broadcaster sends a fresh snapshot instead, so a viewer
+//! that simulates differently (e.g. because the game runs a mod) catches up.
+
+use crate::game::{Game, Input};
+use crate::rules::DefaultRules;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+/// Bumped whenever the messages or the layout of [`Game`] change.
+pub const STREAM_VERSION: u32 = 4;
+pub const DEFAULT_SPECTATE_PORT: u16 = 7100;
+/// Game seconds between two snapshots by default.
+pub const DEFAULT_SNAPSHOT_INTERVAL: f64 = 5.;
+const MAGIC: &str = "ASTEROIDS-STREAM";
+/// Viewers that fall this many bytes behind are disconnected.
+const MAX_PENDING_BYTES: usize = 4 << 20;
+
+#[derive(Serialize, Deserialize)]
+pub enum Message<'a> {
+    /// The whole game, replaces whatever the viewer had.
+    Snapshot(Box<Cow<'a, Game>>),
+    /// One update with the inputs of every player as [`Input::bits`].
+    Update { inputs: Vec<u8>, delta_time: f32 },
+}
+
+struct Viewer {
+    stream: TcpStream,
+    /// Bytes the socket didn't take yet.
+    pending: Vec<u8>,
+}
+
+impl Viewer {
+    /// Queues `data` and writes as much as the socket takes without
+    /// blocking. Returns false once the viewer is gone or too far behind.
+    fn send(&mut self, data: &[u8]) -> bool {
+        self.pending.extend_from_slice(data);
+        while !self.pending.is_empty() {
+            match self.stream.write(&self.pending) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.pending.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(_) => return false,
+            }
+        }
+        self.pending.len() <= MAX_PENDING_BYTES
+    }
+}
+
+/// Accepts viewers on a TCP port and streams a game to them.
+pub struct Broadcaster {
+    listener: TcpListener,
+    viewers: Vec<Viewer>,
+    /// Game seconds between two snapshots, 0 sends one every update.
+    pub snapshot_interval: f64,
+    /// Game time of the last snapshot.
+    last_snapshot: f64,
+}
+
+impl Broadcaster {
+    /// Listens for viewers on `port` on all interfaces.
+    pub fn bind(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            viewers: vec![],
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            last_snapshot: 0.,
+        })
+    }
+
+    /// Number of connected viewers.
+    pub fn viewers(&self) -> usize {
+        self.viewers.len()
+    }
+
+    /// Sends `message` to every viewer, dropping the ones that are gone.
+    fn broadcast(&mut self, message: &Message) {
+        if self.viewers.is_empty() {
+            return;
+        }
+        let mut line = serde_json::to_vec(message).expect("messages are always serializable");
+        line.push(b'\n');
+        self.viewers.retain_mut(|viewer| viewer.send(&line));
+    }
+
+    /// Sends the whole of `game` to every viewer, e.g. after it was replaced by a loaded one.
+    pub fn snapshot(&mut self, game: &Game) {
+        self.broadcast(&Message::Snapshot(Box::new(Cow::Borrowed(game))));
+        self.last_snapshot = game.time;
+        self.accept(game);
+    }
+
+    /// Streams an update that turned the game into `game` using `inputs`,
+    /// and lets new viewers join. Called after every update.
+    pub fn update(&mut self, game: &Game, inputs: &[Input], delta_time: f32) {
+        // The game time goes back when the game restarts
+        let since_snapshot = game.time - self.last_snapshot;
+        if !(0. ..self.snapshot_interval).contains(&since_snapshot) {
+            self.snapshot(game);
+            return;
+        }
+        self.broadcast(&Message::Update {
+            inputs: inputs.iter().map(Input::bits).collect(),
+            delta_time,
+        });
+        // New viewers start from the state after this update
+        self.accept(game);
+    }
+
+    /// Accepts waiting viewers and sends them the header and a snapshot of `game`.
+    fn accept(&mut self, game: &Game) {
+        let mut joined = None;
+        while let Ok((stream, _)) = self.listener.accept() {
+            let data = joined.get_or_insert_with(|| {
+                let snapshot = Message::Snapshot(Box::new(Cow::Borrowed(game)));
+                let json =
+                    serde_json::to_string(&snapshot).expect("messages are always serializable");
+                format!("{MAGIC} {STREAM_VERSION}\n{json}\n").into_bytes()
+            });
+            let _ = stream.set_nodelay(true);
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            let mut viewer = Viewer {
+                stream,
+                pending: vec![],
+            };
+            if viewer.send(data) {
+                self.viewers.push(viewer);
+            }
+        }
+    }
+}
+
+/// Watches the game of a [`Broadcaster`].
+pub struct Spectator {
+    address: String,
+    /// Messages read by a background thread, or why it stopped.
+    receiver: Receiver<Result<Message<'static>, String>>,
+    game: Option<Game>,
+    /// Whether a message arrived since the last (re)connect.
+    live: bool,
+    error: Option<String>,
+}
+
+impl Spectator {
+    /// Connects to the broadcaster at `address` in the background.
+    pub fn connect(address: String) -> Self {
+        Self {
+            receiver: Self::spawn_reader(address.clone()),
+            address,
+            game: None,
+            live: false,
+            error: None,
+        }
+    }
+
+    /// Connects again after the connection ended, the last game stays
+    /// visible until the new snapshot arrives.
+    pub fn reconnect(&mut self) {
+        self.receiver = Self::spawn_reader(self.address.clone());
+        self.live = false;
+        self.error = None;
+    }
+
+    fn spawn_reader(address: String) -> Receiver<Result<Message<'static>, String>> {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = Self::read(&address, |message| sender.send(Ok(message)).is_ok());
+            if let Err(e) = result {
+                let _ = sender.send(Err(e));
+            }
+        });
+        receiver
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Reads messages from `address` until the stream ends or `handle` returns false.
+    fn read(address: &str, mut handle: impl FnMut(Message<'static>) -> bool) -> Result<(), String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("couldn't connect to {address}: {e}"))?;
+        let mut lines = BufReader::new(stream).lines();
+        let header = lines
+            .next()
+            .transpose()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        let expected = format!("{MAGIC} {STREAM_VERSION}");
+        if header != expected {
+            return Err(format!(
+                "unsupported stream '{header}', expected '{expected}'"
+            ));
+        }
+        for line in lines {
+            let line = line.map_err(|e| e.to_string())?;
+            let message =
+                serde_json::from_str(&line).map_err(|e| format!("corrupt message: {e}"))?;
+            if !handle(message) {
+                return Ok(());
+            }
+        }
+        Err("the broadcast ended".to_string())
+    }
+
+    /// Applies every message received since the last call. Returns false
+    /// once the connection is gone, see [`Spectator::error`].
+    pub fn update(&mut self) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(Ok(Message::Snapshot(game))) => {
+                    self.game = Some(game.into_owned());
+                    self.live = true;
+                }
+                Ok(Ok(Message::Update { inputs, delta_time })) => {
+                    if let Some(game) = &mut self.game {
+                        let inputs: Vec<Input> = inputs.into_iter().map(Input::from_bits).collect();
+                        game.update_players(&inputs, delta_time, &mut DefaultRules);
+                    }
+                }
+                Ok(Err(e)) => {
+                    self.error = Some(e);
+                    self.live = false;
+                }
+                Err(TryRecvError::Empty) => return self.error.is_none(),
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// The game as of the last [`Spectator::update`], `None` until the first snapshot arrived.
+    pub fn game(&self) -> Option<&Game> {
+        self.game.as_ref()
+    }
+
+    /// Whether the game is being streamed right now.
+    pub fn is_live(&self) -> bool {
+        self.live
+    }
+
+    /// Why the connection ended.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
@@ -0,0 +1,142 @@
+//! Stress test for the collision checks: a playfield packed with asteroids
+//! and bullets, timed with [`Game::grid`] against testing every pair.
+
+use crate::game::{randf32, Asteroid, Bullet, Collider, Game, FIXED_DELTA_TIME};
+use crate::grid::Grid;
+use crate::math::vec2;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use rand::Rng;
+use std::time::Instant;
+
+/// Bullets kept on the playfield per asteroid.
+const BULLETS_PER_ASTEROID: f32 = 0.25;
+
+/// Results of [`run`].
+pub struct StressReport {
+    pub asteroids: usize,
+    pub bullets: usize,
+    pub updates: u32,
+    /// Mean milliseconds per update.
+    pub update_ms: f64,
+    /// Mean milliseconds to find every bullet inside an asteroid with the grid,
+    /// including building it, and by testing every pair.
+    pub grid_query_ms: f64,
+    pub brute_force_query_ms: f64,
+    /// Asteroid-bullet pairs found in the last state, the same for both methods.
+    pub pairs: usize,
+}
+
+/// A game with `asteroids` asteroids spread over the whole playfield and a
+/// ship that can't crash.
+pub fn stress_game(seed: u64, asteroids: usize) -> Game {
+    let mut game = Game::new(seed);
+    game.god_mode = true;
+    game.asteroids.clear();
+    refill_asteroids(&mut game, asteroids);
+    game
+}
+
+/// Adds asteroids anywhere on the playfield until there are `count`.
+fn refill_asteroids(game: &mut Game, count: usize) {
+    while game.asteroids.len() < count {
+        let rng = &mut game.rng;
+        let pos = vec2(
+            rng.gen::<f32>() * CANVAS_WIDTH as f32,
+            rng.gen::<f32>() * CANVAS_HEIGHT as f32,
+        );
+        let velocity = vec2(randf32(rng) * 25., randf32(rng) * 25.);
+        let scale = 8. * (rng.gen::<f32>() + 1.);
+        let asteroid = Asteroid::new(rng, pos, scale, velocity);
+        game.asteroids.insert(asteroid);
+    }
+}
+
+/// Adds bullets flying in random directions until there are `count`.
+fn refill_bullets(game: &mut Game, count: usize) {
+    while game.bullets.len() < count {
+        let rng = &mut game.rng;
+        let pos = vec2(
+            rng.gen::<f32>() * CANVAS_WIDTH as f32,
+            rng.gen::<f32>() * CANVAS_HEIGHT as f32,
+        );
+        let dir = vec2(randf32(rng), randf32(rng)).normalise();
+        game.bullets.insert(Bullet::new(pos, dir, 0));
+    }
+}
+
+fn brute_force_pairs(game: &Game) -> usize {
+    game.asteroids
+        .iter()
+        .map(|a| {
+            game.bullets
+                .iter()
+                .filter(|b| a.contains(b.pos.x, b.pos.y))
+                .count()
+        })
+        .sum()
+}
+
+fn grid_pairs(game: &Game, grid: &mut Grid<Collider>) -> usize {
+    grid.clear();
+    for (handle, b) in game.bullets.iter_with_handles() {
+        grid.insert_point(b.pos.x, b.pos.y, Collider::Bullet(handle));
+    }
+    game.asteroids
+        .iter()
+        .map(|a| {
+            grid.query(a.bounds())
+                .filter(|c| match **c {
+                    Collider::Bullet(handle) => game
+                        .bullets
+                        .get(handle)
+                        .is_some_and(|b| a.contains(b.pos.x, b.pos.y)),
+                    Collider::Ship(_) => false,
+                })
+                .count()
+        })
+        .sum()
+}
+
+/// Runs `updates` updates of [`stress_game`] with the asteroids and bullets
+/// topped up before every update, then times both ways of finding
+/// asteroid-bullet pairs.
+pub fn run(seed: u64, asteroids: usize, updates: u32) -> StressReport {
+    let mut game = stress_game(seed, asteroids);
+    let bullets = (asteroids as f32 * BULLETS_PER_ASTEROID) as usize;
+
+    let start = Instant::now();
+    for _ in 0..updates {
+        refill_asteroids(&mut game, asteroids);
+        refill_bullets(&mut game, bullets);
+        game.update(&Default::default(), FIXED_DELTA_TIME);
+    }
+    let update_ms = start.elapsed().as_secs_f64() * 1000. / updates.max(1) as f64;
+
+    refill_asteroids(&mut game, asteroids);
+    refill_bullets(&mut game, bullets);
+    let repeats = 20;
+    let mut grid = Grid::default();
+    let start = Instant::now();
+    let mut pairs = 0;
+    for _ in 0..repeats {
+        pairs = grid_pairs(&game, &mut grid);
+    }
+    let grid_query_ms = start.elapsed().as_secs_f64() * 1000. / repeats as f64;
+    let start = Instant::now();
+    let mut brute_force = 0;
+    for _ in 0..repeats {
+        brute_force = brute_force_pairs(&game);
+    }
+    let brute_force_query_ms = start.elapsed().as_secs_f64() * 1000. / repeats as f64;
+    assert_eq!(pairs, brute_force, "the grid missed collisions");
+
+    StressReport {
+        asteroids: game.asteroids.len(),
+        bullets,
+        updates,
+        update_ms,
+        grid_query_ms,
+        brute_force_query_ms,
+        pairs,
+    }
+}
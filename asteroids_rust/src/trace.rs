@@ -0,0 +1,421 @@
+//! Conformance traces for comparing the ports of the game with this one.
+//!
+//! A trace is a JSON Lines file that is easy to write from any language. The
+//! first line is a header:
+//!
+//! ```text
+//! {"format":"asteroids-trace","version":1,"seed":42}
+//! ```
+//!
+//! followed by one line per tick. Tick 0 is the initial state with no input
+//! and a `dt` of 0, every later tick holds the input and delta time of an
+//! update and the state after it:
+//!
+//! ```text
+//! {"tick":1,"dt":0.016666668,"input":4,"score":0,"wave":0,
+//!  "ship":[x,y,vx,vy,rot],"asteroids":[[x,y,vx,vy,scale],..],"bullets":[[x,y,vx,vy],..]}
+//! ```
+//!
+//! (without the line break). `input` is the bitmask described in
+//! [`Input::bits`], positions are in canvas pixels, velocities in pixels per
+//! second and the rotation in radians. A port validates itself by replaying
+//! the seed, inputs and delta times of a reference trace written by
+//! `--trace` and diffing its own trace against it with [`compare`], e.g. with
+//! the `trace_check` binary. Only the seed is recorded, so a trace has to
+//! start from the game [`Game::new`] creates for it: a single player game
+//! with the standard rules, at normal speed and without god mode.
+
+use crate::game::{Game, Input};
+use crate::math::wrap_delta;
+use crate::{CANVAS_HEIGHT, CANVAS_WIDTH};
+use serde::{Deserialize, Serialize};
+use std::{
+    f32::consts::TAU,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+pub const TRACE_FORMAT: &str = "asteroids-trace";
+pub const TRACE_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum TraceError {
+    Io(io::Error),
+    /// The first line isn't a trace header of a supported version.
+    Header,
+    /// A tick line couldn't be parsed, `line` is 1-based.
+    Line {
+        line: usize,
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io(e) => write!(f, "{e}"),
+            TraceError::Header => {
+                write!(f, "not an {TRACE_FORMAT} file of version {TRACE_VERSION}")
+            }
+            TraceError::Line { line, error } => write!(f, "line {line}: {error}"),
+        }
+    }
+}
+
+impl From<io::Error> for TraceError {
+    fn from(e: io::Error) -> Self {
+        TraceError::Io(e)
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TraceHeader {
+    pub format: String,
+    pub version: u32,
+    pub seed: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TraceTick {
+    pub tick: u64,
+    pub dt: f32,
+    pub input: u8,
+    pub score: u32,
+    pub wave: u32,
+    /// `[x, y, velocity_x, velocity_y, rotation]`
+    pub ship: [f32; 5],
+    /// `[x, y, velocity_x, velocity_y, scale]` per asteroid
+    pub asteroids: Vec<[f32; 5]>,
+    /// `[x, y, velocity_x, velocity_y]` per bullet
+    pub bullets: Vec<[f32; 4]>,
+}
+
+impl TraceTick {
+    pub fn new(tick: u64, game: &Game, input: &Input, delta_time: f32) -> Self {
+        let ship = &game.players[0].ship;
+        Self {
+            tick,
+            dt: delta_time,
+            input: input.bits(),
+            score: game.score,
+            wave: game.wave,
+            ship: [
+                ship.transform.pos.x,
+                ship.transform.pos.y,
+                ship.velocity.x,
+                ship.velocity.y,
+                ship.transform.rot,
+            ],
+            asteroids: game
+                .asteroids
+                .iter()
+                .map(|a| {
+                    let t = &a.transform;
+                    [t.pos.x, t.pos.y, a.velocity.x, a.velocity.y, t.scale]
+                })
+                .collect(),
+            bullets: game
+                .bullets
+                .iter()
+                .map(|b| {
+                    let velocity = b.velocity();
+                    [b.pos.x, b.pos.y, velocity.x, velocity.y]
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct Trace {
+    pub header: TraceHeader,
+    pub ticks: Vec<TraceTick>,
+}
+
+impl Trace {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TraceError> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: TraceHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?).map_err(|_| TraceError::Header)?,
+            None => return Err(TraceError::Header),
+        };
+        if header.format != TRACE_FORMAT || header.version != TRACE_VERSION {
+            return Err(TraceError::Header);
+        }
+
+        let mut ticks = vec![];
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let tick = serde_json::from_str(&line)
+                .map_err(|error| TraceError::Line { line: i + 2, error })?;
+            ticks.push(tick);
+        }
+        Ok(Self { header, ticks })
+    }
+}
+
+/// Writes a trace one tick at a time.
+pub struct TraceWriter {
+    file: BufWriter<File>,
+    tick: u64,
+}
+
+impl TraceWriter {
+    /// Creates the file and writes the header and tick 0 for `game`, which
+    /// must be the game [`Game::new`] creates for its seed.
+    pub fn create(path: impl AsRef<Path>, game: &Game) -> io::Result<Self> {
+        if serde_json::to_value(game)? != serde_json::to_value(Game::new(game.seed))? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "traces start from a new game with the standard rules, not a loaded or changed one",
+            ));
+        }
+        let mut writer = Self {
+            file: BufWriter::new(File::create(path)?),
+            tick: 0,
+        };
+        let header = TraceHeader {
+            format: TRACE_FORMAT.to_string(),
+            version: TRACE_VERSION,
+            seed: game.seed,
+        };
+        writer.write_line(&header)?;
+        writer.write_line(&TraceTick::new(0, game, &Input::default(), 0.))?;
+        Ok(writer)
+    }
+
+    /// Records the state of `game` after an update with `input` and `delta_time`.
+    pub fn record(&mut self, game: &Game, input: &Input, delta_time: f32) -> io::Result<()> {
+        self.tick += 1;
+        self.write_line(&TraceTick::new(self.tick, game, input, delta_time))
+    }
+
+    /// Flushes the file, returns the number of recorded updates.
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.file.flush()?;
+        Ok(self.tick)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.file, value)?;
+        writeln!(self.file)
+    }
+}
+
+/// How far a port may drift from the reference before a value is reported.
+#[derive(Clone, Copy)]
+pub struct Tolerance {
+    /// Pixels, measured across the screen wrap.
+    pub position: f32,
+    /// Pixels per second.
+    pub velocity: f32,
+    /// Radians.
+    pub rotation: f32,
+    pub scale: f32,
+    /// Set to skip comparing asteroids, e.g. for ports whose RNG doesn't
+    /// reproduce the asteroid spawns of this one.
+    pub ignore_asteroids: bool,
+    pub ignore_bullets: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            position: 0.01,
+            velocity: 0.01,
+            rotation: 0.001,
+            scale: 0.001,
+            ignore_asteroids: false,
+            ignore_bullets: false,
+        }
+    }
+}
+
+/// A difference between the reference and another trace.
+pub struct Mismatch {
+    pub tick: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "tick {}: {}", self.tick, self.message)
+    }
+}
+
+fn position_error(a: [f32; 2], b: [f32; 2]) -> f32 {
+    let dx = wrap_delta(a[0] - b[0], CANVAS_WIDTH as f32);
+    let dy = wrap_delta(a[1] - b[1], CANVAS_HEIGHT as f32);
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn velocity_error(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+fn rotation_error(a: f32, b: f32) -> f32 {
+    let d = (a - b).rem_euclid(TAU);
+    d.min(TAU - d)
+}
+
+/// Pairs every entity of `reference` with the nearest unpaired one of
+/// `other`, ports aren't required to keep the same order.
+fn pair<const N: usize>(reference: &[[f32; N]], other: &[[f32; N]]) -> Vec<(usize, usize)> {
+    let mut used = vec![false; other.len()];
+    let mut pairs = vec![];
+    for (i, r) in reference.iter().enumerate() {
+        let nearest = (0..other.len()).filter(|j| !used[*j]).min_by(|a, b| {
+            let da = position_error([r[0], r[1]], [other[*a][0], other[*a][1]]);
+            let db = position_error([r[0], r[1]], [other[*b][0], other[*b][1]]);
+            da.total_cmp(&db)
+        });
+        if let Some(j) = nearest {
+            used[j] = true;
+            pairs.push((i, j));
+        }
+    }
+    pairs
+}
+
+fn compare_tick(reference: &TraceTick, other: &TraceTick, tolerance: &Tolerance) -> Vec<String> {
+    let mut errors = vec![];
+    let mut check = |what: String, error: f32, limit: f32| {
+        if error.is_nan() || error > limit {
+            errors.push(format!("{what} differs by {error} (tolerance {limit})"));
+        }
+    };
+
+    let (r, o) = (&reference.ship, &other.ship);
+    check(
+        "ship position".into(),
+        position_error([r[0], r[1]], [o[0], o[1]]),
+        tolerance.position,
+    );
+    check(
+        "ship velocity".into(),
+        velocity_error([r[2], r[3]], [o[2], o[3]]),
+        tolerance.velocity,
+    );
+    check(
+        "ship rotation".into(),
+        rotation_error(r[4], o[4]),
+        tolerance.rotation,
+    );
+
+    if !tolerance.ignore_asteroids {
+        for (i, j) in pair(&reference.asteroids, &other.asteroids) {
+            let (r, o) = (&reference.asteroids[i], &other.asteroids[j]);
+            check(
+                format!("asteroid {i} position"),
+                position_error([r[0], r[1]], [o[0], o[1]]),
+                tolerance.position,
+            );
+            check(
+                format!("asteroid {i} velocity"),
+                velocity_error([r[2], r[3]], [o[2], o[3]]),
+                tolerance.velocity,
+            );
+            check(
+                format!("asteroid {i} scale"),
+                (r[4] - o[4]).abs(),
+                tolerance.scale,
+            );
+        }
+    }
+    if !tolerance.ignore_bullets {
+        for (i, j) in pair(&reference.bullets, &other.bullets) {
+            let (r, o) = (&reference.bullets[i], &other.bullets[j]);
+            check(
+                format!("bullet {i} position"),
+                position_error([r[0], r[1]], [o[0], o[1]]),
+                tolerance.position,
+            );
+            check(
+                format!("bullet {i} velocity"),
+                velocity_error([r[2], r[3]], [o[2], o[3]]),
+                tolerance.velocity,
+            );
+        }
+    }
+
+    if reference.score != other.score {
+        errors.push(format!(
+            "score is {}, expected {}",
+            other.score, reference.score
+        ));
+    }
+    if reference.wave != other.wave {
+        errors.push(format!(
+            "wave is {}, expected {}",
+            other.wave, reference.wave
+        ));
+    }
+    if !tolerance.ignore_asteroids && reference.asteroids.len() != other.asteroids.len() {
+        errors.push(format!(
+            "{} asteroids, expected {}",
+            other.asteroids.len(),
+            reference.asteroids.len()
+        ));
+    }
+    if !tolerance.ignore_bullets && reference.bullets.len() != other.bullets.len() {
+        errors.push(format!(
+            "{} bullets, expected {}",
+            other.bullets.len(),
+            reference.bullets.len()
+        ));
+    }
+    errors
+}
+
+/// Diffs `other` against `reference` tick by tick and returns every value
+/// that is out of `tolerance`. The inputs and delta times have to match
+/// exactly, if they don't the port didn't replay the reference and comparing
+/// further ticks is pointless.
+pub fn compare(reference: &Trace, other: &Trace, tolerance: &Tolerance) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+    if reference.header.seed != other.header.seed {
+        mismatches.push(Mismatch {
+            tick: 0,
+            message: format!(
+                "seed is {}, expected {}",
+                other.header.seed, reference.header.seed
+            ),
+        });
+    }
+    if reference.ticks.len() != other.ticks.len() {
+        mismatches.push(Mismatch {
+            tick: 0,
+            message: format!(
+                "{} ticks, expected {}",
+                other.ticks.len(),
+                reference.ticks.len()
+            ),
+        });
+    }
+
+    for (r, o) in reference.ticks.iter().zip(&other.ticks) {
+        if r.tick != o.tick || r.input != o.input || r.dt != o.dt {
+            mismatches.push(Mismatch {
+                tick: r.tick,
+                message: format!(
+                    "tick {} has input {} and dt {}, expected input {} and dt {}, stopping",
+                    o.tick, o.input, o.dt, r.input, r.dt
+                ),
+            });
+            break;
+        }
+        mismatches.extend(
+            compare_tick(r, o, tolerance)
+                .into_iter()
+                .map(|message| Mismatch {
+                    tick: r.tick,
+                    message,
+                }),
+        );
+    }
+    mismatches
+}
@@ -0,0 +1,179 @@
+//! Weapons a ship can switch between, see [`Input::switch_weapon`](crate::game::Input::switch_weapon).
+//!
+//! Every [`WeaponKind`] fires as its [`Weapon`] stats describe. The blaster
+//! fires the bullets of the original game. The laser hits the first asteroid
+//! along a ray the moment it fires, missiles steer towards the nearest
+//! asteroid and mines stay where the ship dropped them until something
+//! flies into them.
+
+use crate::game::BULLET_SPEED;
+use crate::powerup::SPREAD_ANGLE;
+use crate::CANVAS_HEIGHT;
+use serde::{Deserialize, Serialize};
+
+/// Radians per second a missile turns towards its target.
+pub const MISSILE_TURN_RATE: f32 = 3.;
+/// Seconds a laser beam stays on screen.
+pub const BEAM_DURATION: f64 = 0.1;
+
+/// How long a projectile lasts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lifetime {
+    /// Until it crossed the edge of the screen this many times.
+    Wraps(u8),
+    /// Until it flew this far.
+    Distance(f32),
+    /// For this many seconds.
+    Time(f32),
+}
+
+/// What a weapon fires.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weapon {
+    /// Distance a projectile flies per second, unused by the laser.
+    pub speed: f32,
+    /// How long a projectile lasts, for the laser the length of the ray.
+    pub lifetime: Lifetime,
+    /// Projectiles fired at once, fanned out by `spread`.
+    pub count: u32,
+    /// Angle in radians between neighbouring projectiles.
+    pub spread: f32,
+    /// Factor on the bullet cooldown of the player, which shortens as the score grows.
+    pub cooldown: f64,
+    /// How often a hit splits an asteroid: 1 splits it once, 2 also splits
+    /// its pieces and so on.
+    pub damage: u32,
+}
+
+impl Weapon {
+    /// Distance a projectile flies before it runs out, unlimited for [`Lifetime::Wraps`].
+    pub fn range(&self) -> f32 {
+        match self.lifetime {
+            Lifetime::Wraps(_) => f32::INFINITY,
+            Lifetime::Distance(distance) => distance,
+            Lifetime::Time(seconds) => self.speed * seconds,
+        }
+    }
+
+    /// Angles of the projectiles relative to the ship's heading. The spread
+    /// shot power-up adds one on each side.
+    pub fn angles(&self, spread_shot: bool) -> impl Iterator<Item = f32> {
+        let count = if spread_shot {
+            self.count + 2
+        } else {
+            self.count
+        };
+        let spread = if spread_shot && self.spread == 0. {
+            SPREAD_ANGLE
+        } else {
+            self.spread
+        };
+        (0..count).map(move |i| (i as f32 - (count - 1) as f32 / 2.) * spread)
+    }
+}
+
+const BLASTER: Weapon = Weapon {
+    speed: BULLET_SPEED,
+    lifetime: Lifetime::Wraps(5),
+    count: 1,
+    spread: 0.,
+    cooldown: 1.,
+    damage: 1,
+};
+
+const LASER: Weapon = Weapon {
+    speed: 0.,
+    lifetime: Lifetime::Distance(160.),
+    count: 1,
+    spread: 0.,
+    cooldown: 1.5,
+    damage: 1,
+};
+
+const MISSILE: Weapon = Weapon {
+    speed: 110.,
+    lifetime: Lifetime::Time(3.),
+    count: 1,
+    spread: 0.,
+    cooldown: 2.,
+    damage: 2,
+};
+
+const MINE: Weapon = Weapon {
+    speed: 0.,
+    lifetime: Lifetime::Time(15.),
+    count: 1,
+    spread: 0.,
+    cooldown: 1.5,
+    damage: 3,
+};
+
+/// How far bullets fly with [`Ballistics::ARCADE`], about a screen in any direction.
+pub const ARCADE_BULLET_RANGE: f32 = CANVAS_HEIGHT as f32;
+/// Bullets a player can have out at once with [`Ballistics::ARCADE`].
+pub const ARCADE_MAX_BULLETS: u32 = 4;
+
+/// Changes to how bullets fly, see [`Game::ballistics`](crate::game::Game::ballistics).
+/// The default keeps the rules of this game, where bullets vanish after
+/// crossing the edge of the screen five times.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ballistics {
+    /// Distance after which bullets whose weapon counts screen wraps disappear instead.
+    pub range: Option<f32>,
+    /// Bullets add the velocity of the ship that fired them to their own,
+    /// mines stay where they were dropped.
+    pub inherit_velocity: bool,
+    /// Most bullets, missiles and mines one player can have out at once.
+    pub max_bullets: Option<u32>,
+}
+
+impl Ballistics {
+    /// The rules of the arcade original: bullets keep the ship's momentum,
+    /// fly [`ARCADE_BULLET_RANGE`] and at most [`ARCADE_MAX_BULLETS`] are out at once.
+    pub const ARCADE: Self = Self {
+        range: Some(ARCADE_BULLET_RANGE),
+        inherit_velocity: true,
+        max_bullets: Some(ARCADE_MAX_BULLETS),
+    };
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    /// Bullets from the ship's nose.
+    #[default]
+    Blaster,
+    /// An instant ray from the ship's nose.
+    Laser,
+    /// Bullets that steer towards the nearest asteroid.
+    Missile,
+    /// Bullets that stay behind the ship.
+    Mine,
+}
+
+impl WeaponKind {
+    pub const ALL: [Self; 4] = [Self::Blaster, Self::Laser, Self::Missile, Self::Mine];
+
+    pub fn stats(self) -> &'static Weapon {
+        match self {
+            Self::Blaster => &BLASTER,
+            Self::Laser => &LASER,
+            Self::Missile => &MISSILE,
+            Self::Mine => &MINE,
+        }
+    }
+
+    /// The weapon after this one when switching.
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    /// Name shown on the HUD.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blaster => "BLASTER",
+            Self::Laser => "LASER",
+            Self::Missile => "MISSILE",
+            Self::Mine => "MINE",
+        }
+    }
+}
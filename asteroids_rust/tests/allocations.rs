@@ -0,0 +1,77 @@
+//! Checks that updating a running game doesn't touch the heap once its pools
+//! and scratch buffers have grown to size.
+
+use asteroids_rust::game::{Game, Input, FIXED_DELTA_TIME};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    /// Allocations made by the current thread, other test threads don't count.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Turns, thrusts and fires in a fixed pattern so bullets keep hitting asteroids.
+fn input(tick: u32) -> Input {
+    let mut bits = 16;
+    bits |= match tick / 40 % 4 {
+        0 => 1,
+        1 => 4,
+        2 => 2,
+        _ => 8,
+    };
+    Input::from_bits(bits)
+}
+
+#[test]
+fn steady_state_updates_do_not_allocate() {
+    let mut game = Game::new(7);
+    game.god_mode = true;
+
+    // Lets the pools, the grid and the event list reach their working size
+    for tick in 0..1200 {
+        game.update(&input(tick), FIXED_DELTA_TIME);
+    }
+
+    let mut checked = 0;
+    let mut destroyed = 0;
+    for tick in 1200..4800 {
+        let wave = game.wave;
+        let before = allocations();
+        game.update(&input(tick), FIXED_DELTA_TIME);
+        let allocated = allocations() - before;
+        // A new wave may need more asteroids than any wave before
+        if game.wave != wave {
+            continue;
+        }
+        assert_eq!(allocated, 0, "update {tick} allocated {allocated} times");
+        checked += 1;
+        destroyed += game.events.len();
+    }
+    assert!(checked > 3000, "only {checked} updates were checked");
+    assert!(destroyed > 0, "nothing happened in the checked updates");
+}